@@ -0,0 +1,75 @@
+use std::any::{type_name, TypeId};
+
+/// The set of component types a query reads and writes.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{Access, Component};
+///
+/// struct Position(f32);
+/// impl Component for Position {}
+///
+/// struct Velocity(f32);
+/// impl Component for Velocity {}
+///
+/// let mut movement = Access::default();
+/// movement.add_write::<Position>();
+/// movement.add_read::<Velocity>();
+///
+/// let mut rendering = Access::default();
+/// rendering.add_read::<Position>();
+///
+/// assert_eq!(movement.conflict(), None);
+/// assert!(!movement.is_compatible(&rendering));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Access {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+}
+
+impl Access {
+    /// Records a shared access to `T`.
+    pub fn add_read<T: 'static>(&mut self) {
+        self.reads.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    /// Records an exclusive access to `T`.
+    pub fn add_write<T: 'static>(&mut self) {
+        self.writes.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    /// Records every access of the other set.
+    pub fn extend(&mut self, other: &Access) {
+        self.reads.extend_from_slice(&other.reads);
+        self.writes.extend_from_slice(&other.writes);
+    }
+
+    /// Finds a type that is written more than once or is both written and read. Returns the name
+    /// of that type.
+    pub fn conflict(&self) -> Option<&'static str> {
+        self.writes.iter().enumerate().find_map(|(i, &(id, name))| {
+            let written_again = self.writes[i + 1..].iter().any(|&(other, _)| other == id);
+            let read = self.reads.iter().any(|&(other, _)| other == id);
+
+            (written_again || read).then_some(name)
+        })
+    }
+
+    /// Checks if two accesses can be performed at the same time, that is, neither of them
+    /// writes a type the other one reads or writes.
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        let overlaps = |writes: &[(TypeId, &str)], accesses: &Access| {
+            writes.iter().any(|&(id, _)| {
+                accesses
+                    .reads
+                    .iter()
+                    .chain(&accesses.writes)
+                    .any(|&(other, _)| other == id)
+            })
+        };
+
+        !overlaps(&self.writes, other) && !overlaps(&other.writes, self)
+    }
+}
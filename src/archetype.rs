@@ -1,11 +1,17 @@
-use crate::{Component, EntityId};
-use std::{alloc::Layout, any::TypeId, collections::HashMap};
+use crate::{
+    change::{ComponentTicks, Tick},
+    component_set::{ComponentSet, N_STACK_TYPE_IDS},
+    Component, EntityId, TecsError,
+};
+use smallvec::SmallVec;
+use std::{alloc::Layout, any::TypeId, cell::UnsafeCell, collections::HashMap};
 
 #[derive(Clone, Debug)]
 pub struct TypeInfo {
     pub(crate) id: TypeId,
     pub(crate) layout: Layout,
     pub(crate) drop: unsafe fn(*mut u8),
+    pub(crate) name: &'static str,
 }
 
 impl std::cmp::PartialEq for TypeInfo {
@@ -38,6 +44,7 @@ impl TypeInfo {
             id: TypeId::of::<T>(),
             layout: Layout::new::<T>(),
             drop: drop::<T>,
+            name: std::any::type_name::<T>(),
         }
     }
 }
@@ -52,11 +59,42 @@ pub struct Archetype {
     pub(crate) index: HashMap<TypeId, usize>,
     pub(crate) component_types: Box<[TypeInfo]>,
     pub(crate) components: Box<[*mut u8]>,
+    /// Change ticks of every component, stored column by column next to `components`.
+    pub(crate) ticks: Box<[Vec<UnsafeCell<ComponentTicks>>]>,
     pub(crate) capacity: usize,
     pub(crate) entities: Vec<EntityId>,
+    pub(crate) insert_edges: HashMap<TypeId, usize>,
+    pub(crate) remove_edges: HashMap<TypeId, usize>,
 }
 
+// Safety: the columns only store components, which are `Send` and `Sync`
+unsafe impl Send for Archetype {}
+unsafe impl Sync for Archetype {}
+
 impl Archetype {
+    /// Constructs an empty archetype with given component types sorted by their ids. Columns of
+    /// zero-sized components never allocate and point to a dangling aligned address.
+    pub(crate) fn new(component_types: Box<[TypeInfo]>) -> Self {
+        let components = component_types
+            .iter()
+            .map(|info| match info.layout.size() {
+                0 => std::ptr::without_provenance_mut(info.layout.align()),
+                _ => std::ptr::null_mut(),
+            })
+            .collect();
+
+        Self {
+            capacity: 0,
+            index: HashMap::from_iter(component_types.iter().map(|t| t.id).zip(0..)),
+            components,
+            ticks: component_types.iter().map(|_| vec![]).collect(),
+            entities: vec![],
+            component_types,
+            insert_edges: HashMap::new(),
+            remove_edges: HashMap::new(),
+        }
+    }
+
     /// Checks component `C` in this archetype.
     pub fn contains<C: Component>(&self) -> bool {
         self.component_types
@@ -64,124 +102,348 @@ impl Archetype {
             .is_ok()
     }
 
-    pub(crate) fn alloc(&mut self, cap: usize) {
-        use std::alloc::{alloc, handle_alloc_error};
+    /// The number of entities in this archetype.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
 
-        if cap == 0 {
-            return;
+    /// Checks if there are no entities in this archetype.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// The entities of this archetype in storage order.
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    /// Ids of component types in this archetype sorted in ascending order.
+    pub fn component_ids(&self) -> impl ExactSizeIterator<Item = TypeId> + '_ {
+        self.component_types.iter().map(|info| info.id)
+    }
+
+    /// The index of the archetype an entity moves to after inserting components `S`, if that
+    /// transition has already happened once.
+    pub fn insert_edge<S: ComponentSet>(&self) -> Option<usize> {
+        self.insert_edges.get(&TypeId::of::<S>()).copied()
+    }
+
+    /// The index of the archetype an entity moves to after removing components `S`, if that
+    /// transition has already happened once.
+    pub fn remove_edge<S: ComponentSet>(&self) -> Option<usize> {
+        self.remove_edges.get(&TypeId::of::<S>()).copied()
+    }
+
+    /// All cached insertion edges as pairs of the [`TypeId`] of a component set and the index of
+    /// the target archetype.
+    pub fn insert_edges(&self) -> impl Iterator<Item = (TypeId, usize)> + '_ {
+        self.insert_edges.iter().map(|(&id, &index)| (id, index))
+    }
+
+    /// All cached removal edges as pairs of the [`TypeId`] of a component set and the index of
+    /// the target archetype.
+    pub fn remove_edges(&self) -> impl Iterator<Item = (TypeId, usize)> + '_ {
+        self.remove_edges.iter().map(|(&id, &index)| (id, index))
+    }
+
+    /// The layout of a column of `capacity` components of given type.
+    fn column_layout(type_info: &TypeInfo, capacity: usize) -> Result<Layout, TecsError> {
+        capacity
+            .checked_mul(type_info.layout.size())
+            .and_then(|size| Layout::from_size_align(size, type_info.layout.align()).ok())
+            .ok_or(TecsError::CapacityOverflow)
+    }
+
+    pub(crate) fn reserve(&mut self, min_additional_capacity: usize) {
+        if let Err(error) = self.try_reserve(min_additional_capacity) {
+            error.handle_alloc();
         }
+    }
 
-        for (type_info, components_ptr) in
-            self.component_types.iter().zip(self.components.iter_mut())
-        {
-            if type_info.layout.size() == 0 {
-                continue;
-            }
+    /// Makes room for at least `min_additional_capacity` more entities. The archetype is left
+    /// untouched on failure.
+    pub(crate) fn try_reserve(&mut self, min_additional_capacity: usize) -> Result<(), TecsError> {
+        use std::alloc::{alloc, dealloc};
 
-            let Ok(layout) =
-                Layout::from_size_align(cap * type_info.layout.size(), type_info.layout.align())
-            else {
+        if self.capacity - self.entities.len() >= min_additional_capacity {
+            return Ok(());
+        }
+
+        let next_capacity = match self.capacity {
+            0 => min_additional_capacity,
+            _ => self
+                .capacity
+                .checked_add(min_additional_capacity.max(self.capacity / 2 + 1))
+                .ok_or(TecsError::CapacityOverflow)?,
+        };
+
+        let layouts = self
+            .component_types
+            .iter()
+            .map(|type_info| Self::column_layout(type_info, next_capacity))
+            .collect::<Result<SmallVec<[Layout; N_STACK_TYPE_IDS]>, _>>()?;
+
+        let ticks_layout = Layout::array::<ComponentTicks>(next_capacity)
+            .map_err(|_| TecsError::CapacityOverflow)?;
+
+        for ticks in self.ticks.iter_mut() {
+            ticks
+                .try_reserve_exact(next_capacity - ticks.len())
+                .map_err(|_| TecsError::AllocFailed {
+                    layout: ticks_layout,
+                })?;
+        }
+
+        let mut next_components = SmallVec::<[*mut u8; N_STACK_TYPE_IDS]>::new();
+
+        for (&layout, &components_ptr) in layouts.iter().zip(self.components.iter()) {
+            if layout.size() == 0 {
+                next_components.push(components_ptr);
                 continue;
-            };
+            }
 
             let ptr = unsafe { alloc(layout) };
 
             if ptr.is_null() {
-                handle_alloc_error(layout);
+                for (&layout, &ptr) in layouts.iter().zip(&next_components) {
+                    if layout.size() != 0 {
+                        unsafe { dealloc(ptr, layout) };
+                    }
+                }
+
+                return Err(TecsError::AllocFailed { layout });
             }
 
-            *components_ptr = ptr;
+            next_components.push(ptr);
         }
 
-        self.capacity = cap;
+        for ((type_info, components_ptr), next_ptr) in self
+            .component_types
+            .iter()
+            .zip(self.components.iter_mut())
+            .zip(next_components)
+        {
+            let size = type_info.layout.size();
+
+            if size != 0 && self.capacity != 0 {
+                let layout = Self::column_layout(type_info, self.capacity)?;
+
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        *components_ptr,
+                        next_ptr,
+                        size * self.entities.len(),
+                    );
+                    dealloc(*components_ptr, layout);
+                }
+            }
+
+            *components_ptr = next_ptr;
+        }
+
+        self.capacity = next_capacity;
+
+        Ok(())
     }
 
-    pub(crate) fn reserve(&mut self, min_additional_capacity: usize) {
-        use std::alloc::{handle_alloc_error, realloc};
+    /// Drops the components of the entity at `entity_index` and moves the last entity of this
+    /// archetype into the freed row. Returns the id of the moved entity if there was one.
+    ///
+    /// # Safety
+    ///
+    /// `entity_index` should be less than `self.entities.len()`.
+    pub(crate) unsafe fn swap_remove(&mut self, entity_index: usize) -> Option<EntityId> {
+        for (type_info, &components_ptr) in self.component_types.iter().zip(self.components.iter())
+        {
+            unsafe { (type_info.drop)(components_ptr.add(type_info.layout.size() * entity_index)) };
+        }
 
-        if self.capacity - self.entities.len() >= min_additional_capacity {
-            return;
+        unsafe { self.swap_remove_forget(entity_index) }
+    }
+
+    /// Moves the last entity of this archetype into the row `entity_index` without dropping the
+    /// components stored there. Returns the id of the moved entity if there was one.
+    ///
+    /// # Safety
+    ///
+    /// - `entity_index` should be less than `self.entities.len()`.
+    /// - the components in the row should be already dropped or moved out.
+    pub(crate) unsafe fn swap_remove_forget(&mut self, entity_index: usize) -> Option<EntityId> {
+        let last_index = self.entities.len() - 1;
+
+        if entity_index != last_index {
+            for (type_info, &components_ptr) in
+                self.component_types.iter().zip(self.components.iter())
+            {
+                let size = type_info.layout.size();
+
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        components_ptr.add(size * last_index),
+                        components_ptr.add(size * entity_index),
+                        size,
+                    );
+                }
+            }
         }
 
-        if self.capacity == 0 {
-            self.alloc(min_additional_capacity);
-            return;
+        for ticks in self.ticks.iter_mut() {
+            ticks.swap_remove(entity_index);
         }
 
-        let additional_capacity = min_additional_capacity.max(self.capacity / 2 + 1);
-        let next_capacity = self.capacity + additional_capacity;
+        self.entities.swap_remove(entity_index);
+
+        (entity_index != last_index).then(|| self.entities[entity_index])
+    }
 
-        for (type_info, components_ptr) in
-            self.component_types.iter().zip(self.components.iter_mut())
+    /// Moves the entity at `entity_index` to the end of `target`. Components that `target` does not
+    /// contain are forgotten, components of `target` that this archetype does not contain are left
+    /// uninitialized and marked added at `tick`. Returns the new row of the entity and the id of
+    /// the entity moved in its place in this archetype.
+    ///
+    /// # Safety
+    ///
+    /// - `entity_index` should be less than `self.entities.len()`.
+    /// - components missing from `target` should be already dropped or moved out.
+    /// - missing components of `target` should be written right after this call.
+    pub(crate) unsafe fn move_to(
+        &mut self,
+        entity_index: usize,
+        target: &mut Archetype,
+        tick: Tick,
+    ) -> (usize, Option<EntityId>) {
+        target.reserve(1);
+        let target_index = target.entities.len();
+
+        for (type_info, &components_ptr) in self.component_types.iter().zip(self.components.iter())
         {
-            if type_info.layout.size() == 0 {
+            let Some(&target_component_index) = target.index.get(&type_info.id) else {
                 continue;
+            };
+
+            let target_ptr = target.components[target_component_index];
+            let size = type_info.layout.size();
+
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    components_ptr.add(size * entity_index),
+                    target_ptr.add(size * target_index),
+                    size,
+                );
             }
+        }
 
-            let Ok(prev_layout) = Layout::from_size_align(
-                self.capacity * type_info.layout.size(),
-                type_info.layout.align(),
-            ) else {
-                continue;
+        for (type_info, ticks) in target.component_types.iter().zip(target.ticks.iter_mut()) {
+            let component_ticks = match self.index.get(&type_info.id) {
+                Some(&component_index) => *self.ticks[component_index][entity_index].get_mut(),
+                None => ComponentTicks::new(tick),
             };
 
-            let ptr = unsafe {
-                realloc(
-                    *components_ptr,
-                    prev_layout,
-                    next_capacity * type_info.layout.size(),
-                )
-            };
+            ticks.push(UnsafeCell::new(component_ticks));
+        }
 
-            if ptr.is_null() {
-                handle_alloc_error(prev_layout);
-            }
+        target.entities.push(self.entities[entity_index]);
+
+        let moved = unsafe { self.swap_remove_forget(entity_index) };
+
+        (target_index, moved)
+    }
 
-            *components_ptr = ptr;
+    /// Adds an entity whose components are already written to the end of this archetype marking
+    /// them added at `tick`.
+    pub(crate) fn push_entity(&mut self, entity: EntityId, tick: Tick) {
+        for ticks in self.ticks.iter_mut() {
+            ticks.push(UnsafeCell::new(ComponentTicks::new(tick)));
         }
 
-        self.capacity = next_capacity;
+        self.entities.push(entity);
     }
 
-    pub(crate) unsafe fn write_to_end<C: Component>(&mut self, value: C) {
+    /// Marks component with given id at the row `entity_index` added at `tick`.
+    pub(crate) fn set_added(&mut self, id: TypeId, entity_index: usize, tick: Tick) {
+        if let Some(&component_index) = self.index.get(&id) {
+            *self.ticks[component_index][entity_index].get_mut() = ComponentTicks::new(tick);
+        }
+    }
+
+    /// Drops component with given id at the row `entity_index`.
+    ///
+    /// # Safety
+    ///
+    /// The row should be initialized and the component is treated as uninitialized after this
+    /// call.
+    pub(crate) unsafe fn drop_component(&mut self, id: TypeId, entity_index: usize) {
+        let Some(&component_index) = self.index.get(&id) else {
+            return;
+        };
+
+        let type_info = &self.component_types[component_index];
+        let components_ptr = self.components[component_index];
+
+        unsafe { (type_info.drop)(components_ptr.add(type_info.layout.size() * entity_index)) };
+    }
+
+    /// Pointer to the column of component `C` or `None` if the archetype lacks it. The pointer is
+    /// dangling if the column has no storage.
+    pub(crate) fn column_ptr<C: Component>(&self) -> Option<*mut C> {
+        let &component_index = self.index.get(&TypeId::of::<C>())?;
+        let ptr = self.components[component_index].cast::<C>();
+
+        if ptr.is_null() {
+            Some(std::ptr::NonNull::dangling().as_ptr())
+        } else {
+            Some(ptr)
+        }
+    }
+
+    /// Pointer to the change ticks of component `C` or `None` if the archetype lacks it.
+    pub(crate) fn ticks_ptr<C: Component>(&self) -> Option<*const UnsafeCell<ComponentTicks>> {
+        let &component_index = self.index.get(&TypeId::of::<C>())?;
+
+        Some(self.ticks[component_index].as_ptr())
+    }
+
+    pub(crate) unsafe fn write<C: Component>(&mut self, value: C, entity_index: usize) {
         let index = self.index[&TypeId::of::<C>()];
         let ptr = self.components[index].cast::<C>();
 
         unsafe {
-            ptr.add(self.entities.len()).write(value);
+            ptr.add(entity_index).write(value);
         }
     }
+
+    pub(crate) unsafe fn read<C: Component>(&self, entity_index: usize) -> C {
+        let index = self.index[&TypeId::of::<C>()];
+        let ptr = self.components[index].cast::<C>();
+
+        unsafe { ptr.add(entity_index).read() }
+    }
 }
 
 impl Drop for Archetype {
     fn drop(&mut self) {
         use std::alloc::dealloc;
 
-        if self.capacity == 0 {
-            return;
-        }
+        for (type_info, &components_ptr) in self.component_types.iter().zip(self.components.iter())
+        {
+            let size = type_info.layout.size();
 
-        for (i, &components_ptr) in self.components.iter().enumerate() {
-            let elem_layout = self.component_types[i].layout;
-            let size = elem_layout.size();
-            let drop = self.component_types[i].drop;
+            for j in 0..self.entities.len() {
+                unsafe {
+                    (type_info.drop)(components_ptr.add(size * j));
+                }
+            }
+
+            if size == 0 || self.capacity == 0 {
+                continue;
+            }
 
             let Ok(layout) =
-                Layout::from_size_align(self.capacity * elem_layout.size(), elem_layout.align())
+                Layout::from_size_align(self.capacity * size, type_info.layout.align())
             else {
                 continue;
             };
 
-            if components_ptr.is_null() {
-                continue;
-            }
-
-            for j in 0..self.entities.len() {
-                unsafe {
-                    drop(components_ptr.add(size * j));
-                }
-            }
-
             unsafe {
                 dealloc(components_ptr, layout);
             }
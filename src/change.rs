@@ -0,0 +1,118 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+/// A point in time of the [`World`](crate::World) used to detect changes of components. Every
+/// added or mutably accessed component remembers the tick it happened at.
+pub type Tick = u64;
+
+/// The ticks a query compares component changes against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ChangeTicks {
+    /// Changes that happened after this tick are reported by [`Added`](crate::Added) and
+    /// [`Changed`](crate::Changed).
+    pub last_run: Tick,
+    /// The tick written into components accessed mutably.
+    pub this_run: Tick,
+}
+
+/// The ticks a single component was added and last changed at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComponentTicks {
+    /// The tick the component was added at.
+    pub added: Tick,
+    /// The tick the component was last accessed mutably at.
+    pub changed: Tick,
+}
+
+impl ComponentTicks {
+    pub(crate) fn new(tick: Tick) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+}
+
+/// A mutable reference to a component that marks it changed when dereferenced mutably, see
+/// [`Changed`](crate::Changed).
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, Changed};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Health(u32);
+/// impl Component for Health {}
+///
+/// let mut world = World::new();
+///
+/// let healthy = world.spawn(Health(100));
+/// let wounded = world.spawn(Health(100));
+///
+/// world.clear_trackers();
+///
+/// // only reading does not count as a change
+/// assert_eq!(world.get_mut::<Health>(healthy).unwrap().0, 100);
+/// world.get_mut::<Health>(wounded).unwrap().0 -= 10;
+///
+/// let changed: Vec<_> = world.query_filtered::<&Health, Changed<Health>>().collect();
+///
+/// assert_eq!(changed, [&Health(90)]);
+/// ```
+pub struct Mut<'w, T> {
+    pub(crate) value: &'w mut T,
+    pub(crate) ticks: &'w mut ComponentTicks,
+    pub(crate) this_run: Tick,
+}
+
+impl<'w, T> Mut<'w, T> {
+    /// Marks the component changed without accessing it.
+    pub fn set_changed(&mut self) {
+        self.ticks.changed = self.this_run;
+    }
+
+    /// Accesses the component mutably without marking it changed.
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        self.value
+    }
+
+    /// Converts into a plain mutable reference marking the component changed.
+    pub fn into_inner(mut self) -> &'w mut T {
+        self.set_changed();
+        self.value
+    }
+
+    /// The tick the component was added at.
+    pub fn added_tick(&self) -> Tick {
+        self.ticks.added
+    }
+
+    /// The tick the component was last changed at.
+    pub fn changed_tick(&self) -> Tick {
+        self.ticks.changed
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.set_changed();
+        self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Mut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Mut").field(&self.value).finish()
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{
+    archetype::Archetype,
+    change::ChangeTicks,
+    query::{Fetch, Filter},
+    Component, EntityId,
+};
+use std::{cell::UnsafeCell, marker::PhantomData, slice};
+
+/// A [`Fetch`] that can give out whole columns of an archetype at once. Implemented for `&T`,
+/// `&mut T`, [`Option`] and tuples of them. Not implemented for [`EntityId`], since every chunk
+/// starts with the ids of its entities anyway.
+///
+/// `&mut T` gives out `&mut [T]`, so every component of the slice is marked changed, see
+/// [`Changed`](crate::Changed).
+///
+/// ```rust,compile_fail
+/// use tecs::{World, Component, EntityId};
+///
+/// struct Position(f32);
+/// impl Component for Position {}
+///
+/// let world = World::new();
+///
+/// // the ids are already the first element of `(&[EntityId], &[Position])`
+/// for _ in world.query_chunks::<(EntityId, &Position)>() {}
+/// ```
+///
+/// # Safety
+///
+/// `fetch_slice` should only access the first `len` rows of the prepared archetype.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be fetched as a column of an archetype",
+    note = "the entity ids are always the first element of a chunk, so `EntityId` can be omitted"
+)]
+pub unsafe trait ChunkFetch: Fetch {
+    /// The columns of a single archetype, e.g. `&[T]` for `&T`.
+    type Slice<'w>;
+
+    /// The value yielded for every archetype: the entity ids followed by the slices.
+    type Chunk<'w>;
+
+    /// Fetches the first `len` rows of the prepared archetype.
+    ///
+    /// # Safety
+    ///
+    /// - `len` should not exceed the length of the prepared archetype.
+    /// - the returned slices should not outlive the archetype and alias other mutable borrows.
+    unsafe fn fetch_slice<'w>(state: Self::State, len: usize) -> Self::Slice<'w>;
+
+    /// Fetches the chunk of the archetype with given entities, see [`ChunkFetch::fetch_slice`].
+    ///
+    /// # Safety
+    ///
+    /// `entities` should be the entities of the prepared archetype.
+    unsafe fn fetch_chunk<'w>(entities: &'w [EntityId], state: Self::State) -> Self::Chunk<'w>;
+}
+
+unsafe impl<T: Component> ChunkFetch for &T {
+    type Slice<'w> = &'w [T];
+    type Chunk<'w> = (&'w [EntityId], &'w [T]);
+
+    unsafe fn fetch_slice<'w>(state: Self::State, len: usize) -> Self::Slice<'w> {
+        unsafe { slice::from_raw_parts(state, len) }
+    }
+
+    unsafe fn fetch_chunk<'w>(entities: &'w [EntityId], state: Self::State) -> Self::Chunk<'w> {
+        (entities, unsafe {
+            Self::fetch_slice(state, entities.len())
+        })
+    }
+}
+
+unsafe impl<T: Component> ChunkFetch for &mut T {
+    type Slice<'w> = &'w mut [T];
+    type Chunk<'w> = (&'w [EntityId], &'w mut [T]);
+
+    unsafe fn fetch_slice<'w>(state: Self::State, len: usize) -> Self::Slice<'w> {
+        let (components, ticks, this_run) = state;
+
+        for entity_index in 0..len {
+            unsafe { (*UnsafeCell::raw_get(ticks.add(entity_index))).changed = this_run };
+        }
+
+        unsafe { slice::from_raw_parts_mut(components, len) }
+    }
+
+    unsafe fn fetch_chunk<'w>(entities: &'w [EntityId], state: Self::State) -> Self::Chunk<'w> {
+        (entities, unsafe {
+            Self::fetch_slice(state, entities.len())
+        })
+    }
+}
+
+unsafe impl<Q: ChunkFetch> ChunkFetch for Option<Q> {
+    type Slice<'w> = Option<Q::Slice<'w>>;
+    type Chunk<'w> = (&'w [EntityId], Option<Q::Slice<'w>>);
+
+    unsafe fn fetch_slice<'w>(state: Self::State, len: usize) -> Self::Slice<'w> {
+        state.map(|state| unsafe { Q::fetch_slice(state, len) })
+    }
+
+    unsafe fn fetch_chunk<'w>(entities: &'w [EntityId], state: Self::State) -> Self::Chunk<'w> {
+        (entities, unsafe {
+            Self::fetch_slice(state, entities.len())
+        })
+    }
+}
+
+macro_rules! impl_chunk_fetch {
+    ( $( $t:ident : $T:ident ),+ ) => {
+        unsafe impl< $( $T: ChunkFetch, )+ > ChunkFetch for ( $( $T, )+ ) {
+            type Slice<'w> = ( $( $T::Slice<'w>, )+ );
+            type Chunk<'w> = (&'w [EntityId], $( $T::Slice<'w>, )+ );
+
+            unsafe fn fetch_slice<'w>(state: Self::State, len: usize) -> Self::Slice<'w> {
+                let ( $( $t, )+ ) = state;
+
+                ( $( unsafe { $T::fetch_slice($t, len) }, )+ )
+            }
+
+            unsafe fn fetch_chunk<'w>(
+                entities: &'w [EntityId],
+                state: Self::State,
+            ) -> Self::Chunk<'w> {
+                let ( $( $t, )+ ) = state;
+
+                (entities, $( unsafe { $T::fetch_slice($t, entities.len()) }, )+ )
+            }
+        }
+    };
+}
+
+impl_chunk_fetch! { a: A }
+impl_chunk_fetch! { a: A, b: B }
+impl_chunk_fetch! { a: A, b: B, c: C }
+impl_chunk_fetch! { a: A, b: B, c: C, d: D }
+impl_chunk_fetch! { a: A, b: B, c: C, d: D, e: E }
+impl_chunk_fetch! { a: A, b: B, c: C, d: D, e: E, f: F }
+impl_chunk_fetch! { a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_chunk_fetch! { a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+
+/// An iterator over the columns of archetypes matching a query, created by
+/// [`World::query_chunks`](crate::World::query_chunks) and
+/// [`World::query_chunks_mut`](crate::World::query_chunks_mut). Yields a single chunk per non-empty
+/// archetype.
+pub struct QueryChunks<'w, Q: ChunkFetch, F: Filter = ()> {
+    archetypes: slice::Iter<'w, Archetype>,
+    ticks: ChangeTicks,
+    _query: PhantomData<fn() -> (Q, F)>,
+}
+
+impl<'w, Q: ChunkFetch, F: Filter> QueryChunks<'w, Q, F> {
+    /// # Safety
+    ///
+    /// The archetypes should be borrowed mutably if `Q` is not
+    /// [`ReadOnlyFetch`](crate::ReadOnlyFetch).
+    pub(crate) unsafe fn new(archetypes: &'w [Archetype], ticks: ChangeTicks) -> Self {
+        const {
+            assert!(
+                F::IS_ARCHETYPAL,
+                "chunks can only be filtered by whole archetypes, e.g. with `With` and `Without`",
+            );
+        }
+
+        Self {
+            archetypes: archetypes.iter(),
+            ticks,
+            _query: PhantomData,
+        }
+    }
+}
+
+impl<'w, Q: ChunkFetch, F: Filter> Iterator for QueryChunks<'w, Q, F> {
+    type Item = Q::Chunk<'w>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let archetype = self.archetypes.next()?;
+
+            if archetype.is_empty() || !Q::matches(archetype) || !F::matches(archetype) {
+                continue;
+            }
+
+            let state = Q::prepare(archetype, self.ticks);
+
+            return Some(unsafe { Q::fetch_chunk(archetype.entities(), state) });
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.archetypes.len()))
+    }
+}
+
+impl<Q: ChunkFetch, F: Filter> std::iter::FusedIterator for QueryChunks<'_, Q, F> {}
@@ -0,0 +1,198 @@
+use crate::{ComponentSet, EntityId, World};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// An entity referenced by a [`CommandBuffer`]. Either an existing entity converted from its
+/// [`EntityId`] or one returned by [`CommandBuffer::spawn`]. Commands referencing entities
+/// spawned by other buffers or before the buffer was applied are skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CommandEntity(Target);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Target {
+    Existing(EntityId),
+    /// The `index`-th entity spawned by the buffer with id `buffer`.
+    Spawned {
+        buffer: u64,
+        index: u32,
+    },
+}
+
+impl CommandEntity {
+    /// The id of the entity if it existed before the commands were recorded.
+    pub fn existing(self) -> Option<EntityId> {
+        match self.0 {
+            Target::Existing(id) => Some(id),
+            Target::Spawned { .. } => None,
+        }
+    }
+}
+
+impl From<EntityId> for CommandEntity {
+    fn from(value: EntityId) -> Self {
+        Self(Target::Existing(value))
+    }
+}
+
+/// A unique id for a buffer between two applications.
+fn next_buffer_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+type SpawnFn = Box<dyn FnOnce(&mut World) -> EntityId + Send>;
+type EntityFn = Box<dyn FnOnce(&mut World, EntityId) + Send>;
+
+enum Command {
+    Spawn(SpawnFn),
+    Despawn(CommandEntity),
+    Modify(CommandEntity, EntityFn),
+}
+
+/// A queue of structural changes to the [`World`] recorded while the world is borrowed, e.g.
+/// during a query, and executed later by [`World::apply`]. Component values are stored
+/// type-erased until the buffer is applied.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, CommandBuffer};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Health(u32);
+/// impl Component for Health {}
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Corpse;
+/// impl Component for Corpse {}
+///
+/// let mut world = World::new();
+///
+/// let alive = world.spawn(Health(10));
+/// let dead = world.spawn(Health(0));
+///
+/// let mut commands = CommandBuffer::new();
+///
+/// for (id, health) in world.query::<(tecs::EntityId, &Health)>() {
+///     if health.0 == 0 {
+///         commands.despawn(id);
+///         let corpse = commands.spawn(Corpse);
+///         commands.insert(corpse, Health(1));
+///     }
+/// }
+///
+/// world.apply(&mut commands);
+///
+/// assert!(world.contains(alive));
+/// assert!(!world.contains(dead));
+/// assert_eq!(world.query::<(&Corpse, &Health)>().count(), 1);
+/// assert!(commands.is_empty());
+/// ```
+pub struct CommandBuffer {
+    commands: Vec<Command>,
+    n_spawned: u32,
+    /// Changes on every application, so that handles of spawned entities cannot outlive it.
+    id: u64,
+}
+
+impl Default for CommandBuffer {
+    fn default() -> Self {
+        Self {
+            commands: vec![],
+            n_spawned: 0,
+            id: next_buffer_id(),
+        }
+    }
+}
+
+impl CommandBuffer {
+    /// Constructs new empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of recorded commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Checks if there are no recorded commands.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Records spawning of an entity with given components. The returned handle can be used by
+    /// later commands of the same buffer.
+    pub fn spawn<S: ComponentSet>(&mut self, set: S) -> CommandEntity {
+        let entity = CommandEntity(Target::Spawned {
+            buffer: self.id,
+            index: self.n_spawned,
+        });
+
+        self.n_spawned += 1;
+        self.commands
+            .push(Command::Spawn(Box::new(move |world| world.spawn(set))));
+
+        entity
+    }
+
+    /// Records despawning of an entity, see [`World::despawn`].
+    pub fn despawn(&mut self, entity: impl Into<CommandEntity>) {
+        self.commands.push(Command::Despawn(entity.into()));
+    }
+
+    /// Records insertion of components into an entity, see [`World::insert`].
+    pub fn insert<S: ComponentSet>(&mut self, entity: impl Into<CommandEntity>, set: S) {
+        self.commands.push(Command::Modify(
+            entity.into(),
+            Box::new(move |world, id| {
+                world.insert(id, set);
+            }),
+        ));
+    }
+
+    /// Records removal of components from an entity, see [`World::remove`]. The removed
+    /// components are dropped.
+    pub fn remove<S: ComponentSet>(&mut self, entity: impl Into<CommandEntity>) {
+        self.commands.push(Command::Modify(
+            entity.into(),
+            Box::new(|world, id| {
+                world.remove::<S>(id);
+            }),
+        ));
+    }
+
+    /// Executes the commands in the order they were recorded leaving the buffer empty. Commands
+    /// referencing entities that no longer exist or were spawned by other buffers are skipped.
+    pub(crate) fn apply(&mut self, world: &mut World) {
+        let mut spawned = Vec::with_capacity(self.n_spawned as usize);
+        let id = self.id;
+
+        self.n_spawned = 0;
+        self.id = next_buffer_id();
+
+        let resolve = |spawned: &[EntityId], entity: CommandEntity| match entity.0 {
+            Target::Existing(id) => Some(id),
+            Target::Spawned { buffer, index } if buffer == id => {
+                spawned.get(index as usize).copied()
+            }
+            Target::Spawned { .. } => None,
+        };
+
+        for command in std::mem::take(&mut self.commands) {
+            match command {
+                Command::Spawn(spawn) => spawned.push(spawn(world)),
+                Command::Despawn(entity) => {
+                    if let Some(id) = resolve(&spawned, entity) {
+                        world.despawn(id);
+                    }
+                }
+                Command::Modify(entity, modify) => {
+                    if let Some(id) = resolve(&spawned, entity) {
+                        modify(world, id);
+                    }
+                }
+            }
+        }
+    }
+}
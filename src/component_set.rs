@@ -1,6 +1,4 @@
 use crate::{archetype::TypeInfo, archetype::Archetype, Component};
-use smallvec::SmallVec;
-use std::{any::TypeId, collections::HashMap};
 
 pub(crate) const N_STACK_TYPE_IDS: usize = 32;
 
@@ -8,59 +6,52 @@ pub(crate) const N_STACK_TYPE_IDS: usize = 32;
 ///
 /// # Safety
 ///
-/// - new entity should be added immediately after `write_archetype`.
-/// - `component_infos` should sort `TypeInfo`s by their ids.
-pub unsafe trait ComponentSet: Sized + 'static {
+/// - `write_archetype` should write every component of the pack to the given row.
+/// - `read_archetype` should read every component of the pack from the given row.
+/// - `component_infos` should sort `TypeInfo`s by their ids and panic if some type occurs more
+///   than once.
+pub unsafe trait ComponentSet: Sized + Send + 'static {
     /// The number of components inside this pack.
     const COMPONENT_COUNT: usize;
 
+    /// Writes the components into the row `entity_index` of the archetype. Previous values in the
+    /// row are overwritten without being dropped.
+    ///
+    /// # Safety
+    ///
+    /// - the archetype should contain all the components of the pack.
+    /// - the archetype should have capacity for the row `entity_index`.
+    /// - if `entity_index` is the end of the archetype, new entity should be added immediately
+    ///   after this call.
+    unsafe fn write_archetype(self, archetype: &mut Archetype, entity_index: usize);
+
+    /// Moves the components out of the row `entity_index` of the archetype.
+    ///
     /// # Safety
     ///
-    /// New entity should be added immediately after this call.
-    unsafe fn write_archetype(self, archetype: &mut Archetype);
+    /// - the archetype should contain all the components of the pack.
+    /// - the row should be initialized and it is treated as uninitialized after this call.
+    unsafe fn read_archetype(archetype: &Archetype, entity_index: usize) -> Self;
 
     /// The information about each type in this type pack. Should be sorted by id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pack contains some component more than once. Every operation writing a pack
+    /// into the world calls this function before writing anything.
     fn component_infos() -> impl AsRef<[TypeInfo]>;
-
-    /// Gives an index at which a given component pack lies.
-    fn get_index(index: &HashMap<Box<[TypeId]>, usize>) -> Option<usize> {
-        let ids: SmallVec<[TypeId; N_STACK_TYPE_IDS]> = Self::component_infos()
-            .as_ref()
-            .iter()
-            .map(|info| info.id)
-            .collect();
-
-        index.get(&ids[..]).copied()
-    }
-
-    /// Similar to `component_infos` but contains only IDs.
-    fn component_ids() -> Box<[TypeId]> {
-        Self::component_infos()
-            .as_ref()
-            .iter()
-            .map(|info| info.id)
-            .collect()
-    }
-
-    /// Creates an archetype based on this component pack.
-    fn make_archetype() -> Archetype {
-        let types: Box<[TypeInfo]> = Self::component_infos().as_ref().to_owned().into();
-
-        Archetype {
-            capacity: 0,
-            index: HashMap::from_iter(types.iter().map(|t| t.id).zip(0..)),
-            components: vec![std::ptr::null_mut(); types.len()].into(),
-            entities: vec![],
-            component_types: types,
-        }
-    }
 }
 
 unsafe impl<T: Component> ComponentSet for T {
     const COMPONENT_COUNT: usize = <(T,) as ComponentSet>::COMPONENT_COUNT;
 
-    unsafe fn write_archetype(self, archetype: &mut Archetype) {
-        unsafe { (self,).write_archetype(archetype) };
+    unsafe fn write_archetype(self, archetype: &mut Archetype, entity_index: usize) {
+        unsafe { (self,).write_archetype(archetype, entity_index) };
+    }
+
+    unsafe fn read_archetype(archetype: &Archetype, entity_index: usize) -> Self {
+        let (value,) = unsafe { <(T,) as ComponentSet>::read_archetype(archetype, entity_index) };
+        value
     }
 
     fn component_infos() -> impl AsRef<[TypeInfo]> {
@@ -80,15 +71,18 @@ macro_rules! impl_tuple_component_set {
         unsafe impl< $( $T: Component, )+ > ComponentSet for ( $( $T, )+ ) {
             const COMPONENT_COUNT: usize = impl_tuple_component_set!( @~count $( $T )+ );
 
-            unsafe fn write_archetype(self, archetype: &mut Archetype) {
-                archetype.reserve(Self::COMPONENT_COUNT);
+            unsafe fn write_archetype(self, archetype: &mut Archetype, entity_index: usize) {
                 let ( $( $t, )+ ) = self;
 
                 $(
-                    unsafe { archetype.write_to_end($t) };
+                    unsafe { archetype.write($t, entity_index) };
                 )+
             }
 
+            unsafe fn read_archetype(archetype: &Archetype, entity_index: usize) -> Self {
+                ( $( unsafe { archetype.read::< $T >(entity_index) }, )+ )
+            }
+
             fn component_infos() -> impl AsRef<[TypeInfo]> {
                 let mut ids = [
                     $(
@@ -96,6 +90,15 @@ macro_rules! impl_tuple_component_set {
                     )+
                 ];
                 ids.sort_unstable_by_key(|info| info.id);
+
+                if let Some(pair) = ids.windows(2).find(|pair| pair[0].id == pair[1].id) {
+                    panic!(
+                        "component set `{}` contains component `{}` more than once",
+                        std::any::type_name::<Self>(),
+                        pair[0].name,
+                    );
+                }
+
                 ids
             }
         }
@@ -0,0 +1,75 @@
+use std::fmt;
+
+/// Lightweight generational handle to an entity.
+///
+/// The `index` addresses a slot inside the [`World`](crate::World) and the `generation` tells
+/// apart different entities that occupied the same slot over time. A handle to a despawned entity
+/// never refers to an entity spawned later in its place.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, Entity};
+///
+/// struct Bullet;
+/// impl Component for Bullet {}
+///
+/// let mut world = World::new();
+///
+/// let old = world.spawn(Bullet);
+/// world.despawn(old);
+///
+/// let new = world.spawn(Bullet);
+///
+/// assert_eq!(old.index(), new.index());
+/// assert_ne!(old, new);
+/// assert!(!world.contains(old));
+/// assert_eq!(Entity::from_bits(new.to_bits()), new);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entity {
+    pub(crate) index: u32,
+    pub(crate) generation: u32,
+}
+
+impl Entity {
+    /// Constructs an entity handle from its index and generation.
+    pub const fn new(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    /// The index of the slot this entity occupies.
+    pub const fn index(self) -> u32 {
+        self.index
+    }
+
+    /// The number of times the slot of this entity has been reused.
+    pub const fn generation(self) -> u32 {
+        self.generation
+    }
+
+    /// Packs the handle into a single `u64`. The generation occupies the high half.
+    pub const fn to_bits(self) -> u64 {
+        ((self.generation as u64) << 32) | self.index as u64
+    }
+
+    /// Unpacks the handle previously packed with [`Entity::to_bits`].
+    pub const fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+impl fmt::Debug for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
@@ -0,0 +1,106 @@
+use crate::EntityId;
+use std::{alloc::Layout, fmt};
+
+/// Errors returned by the fallible API of the [`World`](crate::World).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TecsError {
+    /// The entity does not exist or has been despawned.
+    NoSuchEntity(EntityId),
+    /// The entity exists but lacks the component.
+    MissingComponent {
+        /// The entity that lacks the component.
+        entity: EntityId,
+        /// The type name of the component.
+        component: &'static str,
+    },
+    /// The world has no resource of the type.
+    MissingResource {
+        /// The type name of the resource.
+        resource: &'static str,
+    },
+    /// The ordering constraints of a [`Schedule`](crate::Schedule) form a cycle.
+    ScheduleCycle {
+        /// The names of the systems in the cycle, each one required to run before the next one
+        /// and the last one before the first one.
+        systems: Vec<String>,
+    },
+    /// Several systems of a [`Schedule`](crate::Schedule) have the same label.
+    DuplicateLabel {
+        /// The repeated label.
+        label: String,
+    },
+    /// An ordering constraint of a [`Schedule`](crate::Schedule) names a label or set that no
+    /// system has.
+    UnknownLabel {
+        /// The unknown label or set.
+        label: String,
+    },
+    /// The requested capacity exceeds the maximum size of an allocation.
+    CapacityOverflow,
+    /// The allocator failed to allocate memory.
+    AllocFailed {
+        /// The layout of the failed allocation.
+        layout: Layout,
+    },
+}
+
+impl TecsError {
+    pub(crate) fn missing_component<C: 'static>(entity: EntityId) -> Self {
+        Self::MissingComponent {
+            entity,
+            component: std::any::type_name::<C>(),
+        }
+    }
+
+    pub(crate) fn missing_resource<R: 'static>() -> Self {
+        Self::MissingResource {
+            resource: std::any::type_name::<R>(),
+        }
+    }
+
+    /// Reports an allocation error the same way the standard collections do.
+    pub(crate) fn handle_alloc(self) -> ! {
+        match self {
+            Self::AllocFailed { layout } => std::alloc::handle_alloc_error(layout),
+            error => panic!("{error}"),
+        }
+    }
+}
+
+impl fmt::Display for TecsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchEntity(entity) => write!(f, "entity {entity} does not exist"),
+            Self::MissingComponent { entity, component } => {
+                write!(f, "entity {entity} does not have component `{component}`")
+            }
+            Self::MissingResource { resource } => {
+                write!(f, "resource `{resource}` does not exist")
+            }
+            Self::ScheduleCycle { systems } => {
+                write!(f, "systems form an ordering cycle: ")?;
+
+                for name in systems {
+                    write!(f, "`{name}` -> ")?;
+                }
+
+                match systems.first() {
+                    Some(first) => write!(f, "`{first}`"),
+                    None => Ok(()),
+                }
+            }
+            Self::DuplicateLabel { label } => {
+                write!(f, "label `{label}` is used by more than one system")
+            }
+            Self::UnknownLabel { label } => {
+                write!(f, "no system has label or is in set `{label}`")
+            }
+            Self::CapacityOverflow => write!(f, "capacity overflow"),
+            Self::AllocFailed { layout } => {
+                write!(f, "memory allocation of {} bytes failed", layout.size())
+            }
+        }
+    }
+}
+
+impl std::error::Error for TecsError {}
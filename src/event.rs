@@ -0,0 +1,282 @@
+use crate::Resource;
+use std::{fmt, marker::PhantomData};
+
+/// Signifies that given type can be sent through [`Events`].
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::Event;
+///
+/// struct Collision(u32, u32);
+/// impl Event for Collision {}
+/// ```
+pub trait Event: Send + Sync + 'static {}
+
+/// A double-buffered queue of events of type `E`. Events live exactly two update cycles: they
+/// are sent into the newer buffer, moved into the older one by the next [`Events::update`] and
+/// dropped by the one after it. Every consumer reads events with its own [`EventCursor`], so
+/// that it sees each event once regardless of the other consumers.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{Event, EventCursor, Events};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Damage(u32);
+/// impl Event for Damage {}
+///
+/// let mut events = Events::<Damage>::new();
+/// let mut audio = EventCursor::new();
+/// let mut ui = EventCursor::new();
+///
+/// events.send(Damage(10));
+/// assert_eq!(audio.read(&events).collect::<Vec<_>>(), [&Damage(10)]);
+///
+/// events.update();
+/// events.send(Damage(5));
+///
+/// assert_eq!(audio.read(&events).collect::<Vec<_>>(), [&Damage(5)]);
+/// assert_eq!(ui.read(&events).collect::<Vec<_>>(), [&Damage(10), &Damage(5)]);
+///
+/// events.update();
+/// events.update();
+///
+/// assert!(events.is_empty());
+/// ```
+pub struct Events<E> {
+    older: Vec<E>,
+    newer: Vec<E>,
+    /// The id of the first event of the older buffer.
+    older_start: usize,
+    /// The id of the first event of the newer buffer.
+    newer_start: usize,
+}
+
+impl<E: Event> Resource for Events<E> {}
+
+impl<E> Default for Events<E> {
+    fn default() -> Self {
+        Self {
+            older: vec![],
+            newer: vec![],
+            older_start: 0,
+            newer_start: 0,
+        }
+    }
+}
+
+impl<E> Events<E> {
+    /// Constructs new empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends an event to every consumer.
+    pub fn send(&mut self, event: E) {
+        self.newer.push(event);
+    }
+
+    /// Sends every event of the iterator.
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = E>) {
+        self.newer.extend(events);
+    }
+
+    /// Drops the events of the older buffer and makes the newer buffer older. Should be called
+    /// once per frame.
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.older, &mut self.newer);
+        self.newer.clear();
+
+        self.older_start = self.newer_start;
+        self.newer_start += self.older.len();
+    }
+
+    /// The number of stored events.
+    pub fn len(&self) -> usize {
+        self.older.len() + self.newer.len()
+    }
+
+    /// Checks if there are no stored events.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every stored event. Consumers will not see them.
+    pub fn clear(&mut self) {
+        self.newer_start = self.end();
+        self.older_start = self.newer_start;
+        self.older.clear();
+        self.newer.clear();
+    }
+
+    /// The id of the next sent event.
+    fn end(&self) -> usize {
+        self.newer_start + self.newer.len()
+    }
+
+    /// Stored events with ids starting from `start`.
+    fn since(&self, start: usize) -> impl ExactSizeIterator<Item = &E> + '_ {
+        let older = &self.older[start.saturating_sub(self.older_start).min(self.older.len())..];
+        let newer = &self.newer[start.saturating_sub(self.newer_start).min(self.newer.len())..];
+
+        EventIter {
+            older: older.iter(),
+            newer: newer.iter(),
+        }
+    }
+}
+
+impl<E> fmt::Debug for Events<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Events")
+            .field("len", &self.len())
+            .field("older_start", &self.older_start)
+            .field("newer_start", &self.newer_start)
+            .finish()
+    }
+}
+
+/// Events read by [`EventCursor::read`] in the order they were sent.
+struct EventIter<'a, E> {
+    older: std::slice::Iter<'a, E>,
+    newer: std::slice::Iter<'a, E>,
+}
+
+impl<'a, E> Iterator for EventIter<'a, E> {
+    type Item = &'a E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.older.next().or_else(|| self.newer.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.older.len() + self.newer.len();
+        (len, Some(len))
+    }
+}
+
+impl<E> ExactSizeIterator for EventIter<'_, E> {}
+
+/// The position of a single consumer in [`Events`]. A new cursor starts at the oldest stored
+/// event.
+pub struct EventCursor<E> {
+    next: usize,
+    _event: PhantomData<fn() -> E>,
+}
+
+impl<E> Default for EventCursor<E> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            _event: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for EventCursor<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for EventCursor<E> {}
+
+impl<E> fmt::Debug for EventCursor<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventCursor")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<E> EventCursor<E> {
+    /// Constructs a cursor starting at the oldest stored event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the events sent since the previous read moving the cursor past them.
+    pub fn read<'a>(
+        &mut self,
+        events: &'a Events<E>,
+    ) -> impl ExactSizeIterator<Item = &'a E> + use<'a, E> {
+        let unread = events.since(self.next);
+        self.next = events.end();
+        unread
+    }
+
+    /// The number of events the next read will return.
+    pub fn len(&self, events: &Events<E>) -> usize {
+        events.since(self.next).len()
+    }
+
+    /// Checks if there are no unread events.
+    pub fn is_empty(&self, events: &Events<E>) -> bool {
+        self.len(events) == 0
+    }
+
+    /// Moves the cursor past every stored event without reading them.
+    pub fn clear(&mut self, events: &Events<E>) {
+        self.next = events.end();
+    }
+
+    /// Binds the cursor to the events, see [`EventReader`].
+    pub fn reader<'a>(&'a mut self, events: &'a Events<E>) -> EventReader<'a, E> {
+        EventReader {
+            events,
+            cursor: self,
+        }
+    }
+}
+
+/// A consumer of [`Events`] bound to its [`EventCursor`].
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Event, EventCursor, Events};
+///
+/// struct Spawned(u32);
+/// impl Event for Spawned {}
+///
+/// let mut world = World::new();
+/// world.add_event::<Spawned>();
+///
+/// world.send_event(Spawned(1));
+/// world.send_event(Spawned(2));
+///
+/// let mut cursor = EventCursor::new();
+/// let mut reader = cursor.reader(world.resource::<Events<Spawned>>());
+///
+/// assert_eq!(reader.len(), 2);
+/// assert_eq!(reader.read().map(|event| event.0).sum::<u32>(), 3);
+/// assert!(reader.is_empty());
+/// ```
+pub struct EventReader<'a, E> {
+    events: &'a Events<E>,
+    cursor: &'a mut EventCursor<E>,
+}
+
+impl<'a, E> EventReader<'a, E> {
+    /// Reads the events sent since the previous read.
+    pub fn read(&mut self) -> impl ExactSizeIterator<Item = &'a E> + use<'a, E> {
+        self.cursor.read(self.events)
+    }
+
+    /// The number of unread events.
+    pub fn len(&self) -> usize {
+        self.cursor.len(self.events)
+    }
+
+    /// Checks if there are no unread events.
+    pub fn is_empty(&self) -> bool {
+        self.cursor.is_empty(self.events)
+    }
+
+    /// Skips every unread event.
+    pub fn clear(&mut self) {
+        self.cursor.clear(self.events);
+    }
+}
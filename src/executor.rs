@@ -0,0 +1,321 @@
+use crate::{
+    schedule::{ScheduleGraph, SystemConfig},
+    system::System,
+    World,
+};
+use std::{
+    any::Any,
+    collections::BTreeSet,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+/// Decides how a [`Schedule`](crate::Schedule) runs its systems.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ExecutorKind {
+    /// Runs systems one at a time on the calling thread in a fixed order. Useful for debugging.
+    SingleThreaded,
+    /// Runs systems with compatible accesses at the same time on the calling thread and a pool of
+    /// worker threads owned by the schedule, see
+    /// [`SystemAccess::is_compatible`](crate::SystemAccess::is_compatible).
+    #[default]
+    MultiThreaded,
+}
+
+/// Runs the systems one by one in the sorted order.
+pub(crate) fn run_single_threaded(
+    systems: &mut [SystemConfig],
+    graph: &ScheduleGraph,
+    world: &mut World,
+) {
+    let mut pending = Vec::new();
+
+    for &index in &graph.order {
+        if graph.dependencies[index]
+            .iter()
+            .any(|dependency| pending.contains(dependency))
+        {
+            for dependency in pending.drain(..) {
+                systems[dependency].system.apply_deferred(world);
+            }
+        }
+
+        let system = &mut systems[index].system;
+
+        // Safety: the system is initialized and the world is borrowed mutably
+        unsafe { system.run_unsafe(world) };
+
+        if system.has_deferred() {
+            pending.push(index);
+        }
+    }
+
+    for index in pending {
+        systems[index].system.apply_deferred(world);
+    }
+}
+
+/// A system sent to a worker thread together with the world it runs against.
+struct Job {
+    index: usize,
+    system: *mut dyn System,
+    world: *const World,
+}
+
+// Safety: the system is `Send`, the world is `Sync`, and both outlive the job, see
+// `RunningJobs`
+unsafe impl Send for Job {}
+
+type Done = (usize, Result<(), Box<dyn Any + Send>>);
+
+/// Worker threads of a [`Schedule`](crate::Schedule) kept alive between its runs, so that
+/// running systems in parallel does not spawn threads every frame.
+pub(crate) struct WorkerPool {
+    job_sender: Option<mpsc::Sender<Job>>,
+    done_receiver: mpsc::Receiver<Done>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    /// Spawns `n_threads` worker threads.
+    pub fn new(n_threads: usize) -> Self {
+        let (job_sender, job_receiver) = mpsc::channel::<Job>();
+        let (done_sender, done_receiver) = mpsc::channel::<Done>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let threads = (0..n_threads)
+            .map(|n| {
+                let job_receiver = Arc::clone(&job_receiver);
+                let done_sender = done_sender.clone();
+
+                thread::Builder::new()
+                    .name(format!("tecs-worker-{n}"))
+                    .spawn(move || {
+                        loop {
+                            let job = job_receiver.lock().unwrap().recv();
+
+                            let Ok(Job {
+                                index,
+                                system,
+                                world,
+                            }) = job
+                            else {
+                                break;
+                            };
+
+                            // Safety: the system is initialized, systems running at the same time
+                            // have compatible accesses and the pointers are valid until the job
+                            // is reported done
+                            let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+                                (*system).run_unsafe(&*world)
+                            }));
+
+                            if done_sender.send((index, result)).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                    .expect("failed to spawn a worker thread")
+            })
+            .collect();
+
+        Self {
+            job_sender: Some(job_sender),
+            done_receiver,
+            threads,
+        }
+    }
+
+    /// The number of worker threads.
+    pub fn len(&self) -> usize {
+        self.threads.len()
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // closing the channel stops the workers
+        self.job_sender = None;
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Systems running on the worker threads. Waits for them when dropped, so that the jobs never
+/// outlive the systems and the world they point to, even if the calling thread unwinds.
+struct RunningJobs<'p> {
+    pool: &'p WorkerPool,
+    indices: Vec<usize>,
+}
+
+impl RunningJobs<'_> {
+    fn start(&mut self, job: Job) {
+        self.indices.push(job.index);
+        self.pool
+            .job_sender
+            .as_ref()
+            .expect("the workers are alive until the pool is dropped")
+            .send(job)
+            .expect("the workers are alive until the pool is dropped");
+    }
+
+    fn wait(&mut self) -> Done {
+        let (index, result) = self
+            .pool
+            .done_receiver
+            .recv()
+            .expect("the workers are alive until the pool is dropped");
+
+        self.indices.retain(|&other| other != index);
+        (index, result)
+    }
+}
+
+impl Drop for RunningJobs<'_> {
+    fn drop(&mut self) {
+        while !self.indices.is_empty() {
+            match self.pool.done_receiver.recv() {
+                Ok((index, _)) => self.indices.retain(|&other| other != index),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// Runs the systems on the worker threads and the calling thread. Every system starts once the
+/// systems it is ordered after have finished and no running system conflicts with it. When the
+/// only systems left to start wait for deferred changes, the changes are applied as soon as the
+/// running systems finish.
+pub(crate) fn run_multi_threaded(
+    systems: &mut [SystemConfig],
+    graph: &ScheduleGraph,
+    world: &mut World,
+    pool: &WorkerPool,
+) {
+    let n_systems = systems.len();
+
+    let mut rank = vec![0; n_systems];
+
+    for (position, &index) in graph.order.iter().enumerate() {
+        rank[index] = position;
+    }
+
+    // the systems are only accessed through these pointers while they run
+    let pointers: Vec<*mut dyn System> = systems
+        .iter_mut()
+        .map(|config| &mut *config.system as *mut dyn System)
+        .collect();
+
+    let mut n_waiting: Vec<usize> = graph.dependencies.iter().map(Vec::len).collect();
+
+    // ranks of the systems whose dependencies have finished
+    let mut ready: BTreeSet<usize> = (0..n_systems)
+        .filter(|&index| n_waiting[index] == 0)
+        .map(|index| rank[index])
+        .collect();
+    let mut pending = Vec::new();
+    let mut n_finished = 0;
+    let mut panic = None;
+
+    let mut running = RunningJobs {
+        pool,
+        indices: vec![],
+    };
+
+    loop {
+        let mut local = None;
+
+        if panic.is_none() {
+            let started: Vec<_> = ready
+                .iter()
+                .map(|&position| graph.order[position])
+                .filter(|&index| {
+                    !graph.dependencies[index]
+                        .iter()
+                        .any(|dependency| pending.contains(dependency))
+                })
+                .collect();
+
+            for index in started {
+                if local.is_some() {
+                    break;
+                }
+
+                let is_compatible = running
+                    .indices
+                    .iter()
+                    .all(|&other| graph.accesses[other].is_compatible(&graph.accesses[index]));
+
+                if !is_compatible {
+                    continue;
+                }
+
+                ready.remove(&rank[index]);
+
+                if running.indices.len() < pool.len() {
+                    running.start(Job {
+                        index,
+                        system: pointers[index],
+                        world: &*world,
+                    });
+                } else {
+                    local = Some(index);
+                }
+            }
+        }
+
+        let (index, result) = if let Some(index) = local {
+            let system = pointers[index];
+
+            // Safety: the system is initialized, it is compatible with the running systems and
+            // no other thread accesses it
+            let result =
+                panic::catch_unwind(AssertUnwindSafe(|| unsafe { (*system).run_unsafe(world) }));
+
+            (index, result)
+        } else if !running.indices.is_empty() {
+            running.wait()
+        } else if panic.is_some() || pending.is_empty() {
+            break;
+        } else {
+            // sync point
+            for index in pending.drain(..) {
+                let system = pointers[index];
+
+                // Safety: no system is running
+                unsafe { (*system).apply_deferred(world) };
+            }
+
+            continue;
+        };
+
+        if let Err(payload) = result {
+            panic.get_or_insert(payload);
+            continue;
+        }
+
+        n_finished += 1;
+
+        // Safety: the system has finished running
+        if unsafe { (*pointers[index]).has_deferred() } {
+            pending.push(index);
+        }
+
+        for &dependent in &graph.dependents[index] {
+            n_waiting[dependent] -= 1;
+
+            if n_waiting[dependent] == 0 {
+                ready.insert(rank[dependent]);
+            }
+        }
+    }
+
+    if let Some(payload) = panic {
+        panic::resume_unwind(payload);
+    }
+
+    debug_assert_eq!(n_finished, n_systems, "every system runs once");
+}
@@ -61,7 +61,7 @@
 //! let mut can_fly_names = vec![];
 //!
 //! // you can also opt `EntityId` out if you want
-//! for (name, _can_fly, color) in world.query_mut::<(&Name, &CanFly, &mut Color)>() {
+//! for (name, _can_fly, mut color) in world.query_mut::<(&Name, &CanFly, &mut Color)>() {
 //!     can_fly_names.push(name.clone());
 //!     // you can modify world through the `query_mut`
 //!     *color = Color::Green;
@@ -72,15 +72,51 @@
 
 #![deny(missing_docs)]
 
+mod access;
 mod archetype;
+mod change;
+mod chunk;
+mod command;
 mod component_set;
+mod entity;
+mod error;
+mod event;
+mod executor;
+mod local_world;
 mod query;
+mod removal;
+mod resource;
+mod schedule;
+mod system;
+mod system_param;
 mod world;
 
-pub use world::{Component, World};
+pub use access::Access;
+pub use archetype::Archetype;
+pub use change::{ChangeTicks, ComponentTicks, Mut, Tick};
+pub use chunk::{ChunkFetch, QueryChunks};
+pub use command::{CommandBuffer, CommandEntity};
+pub use component_set::ComponentSet;
+pub use entity::Entity;
+pub use error::TecsError;
+pub use event::{Event, EventCursor, EventReader, Events};
+pub use executor::ExecutorKind;
+pub use local_world::LocalWorld;
+pub use query::{
+    Added, AnyOf, Changed, Fetch, Filter, Has, Or, QueryIter, ReadOnlyFetch, With, Without,
+};
+pub use removal::RemovalRetention;
+pub use resource::Resource;
+pub use schedule::{IntoSystemConfig, IsSystemConfig, Schedule, SetConfig, SystemConfig};
+pub use system::{
+    FunctionSystem, IntoSystem, IsFunctionSystem, IsSystem, System, SystemAccess, SystemMeta,
+    SystemParamFunction,
+};
+pub use system_param::{Commands, Query, Res, ResMut, SystemParam, SystemParamItem};
+pub use world::{Component, EntityHandle, EntityHandleMut, World};
 
-/// Lightweight handle to an entity.
-pub type EntityId = u32;
+/// Lightweight handle to an entity. An alias for [`Entity`].
+pub type EntityId = Entity;
 
 /// The location of a given entity in the data structure.
 #[derive(Clone, Debug, PartialEq, Copy, Eq, PartialOrd, Ord, Hash)]
@@ -234,7 +270,7 @@ mod tests {
         world.spawn((Name("Alex"), Height(67.0), Age(16)));
         world.spawn((Name("Steve"), Height(67.0), Age(17)));
 
-        for (_entity, name, _age) in world.query_mut::<(EntityId, &mut Name, &Age)>() {
+        for (_entity, mut name, _age) in world.query_mut::<(EntityId, &mut Name, &Age)>() {
             *name = Name("None");
         }
 
@@ -242,4 +278,1365 @@ mod tests {
             .query_mut::<(&Name,)>()
             .all(|(name,)| name == &Name("None")));
     }
+
+    #[test]
+    fn despawn_relocates_moved_entity() {
+        let mut world = World::default();
+
+        let entities = [
+            world.spawn((Name("John"), Age(18))),
+            world.spawn((Name("Hannah"), Age(24))),
+            world.spawn((Name("Bob"), Age(31))),
+        ];
+
+        assert!(world.despawn(entities[0]));
+        assert!(!world.despawn(entities[0]));
+        assert!(!world.contains(entities[0]));
+
+        assert_eq!(world.get::<Name>(entities[2]), Some(&Name("Bob")));
+        assert_eq!(world.get::<Age>(entities[1]), Some(&Age(24)));
+
+        assert_eq!(
+            world.query::<(EntityId, &Name)>().collect::<Vec<_>>(),
+            [(entities[2], &Name("Bob")), (entities[1], &Name("Hannah"))],
+        );
+    }
+
+    #[test]
+    fn despawn_drops_components() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        struct Counted(Arc<AtomicUsize>);
+        impl Component for Counted {}
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut world = World::default();
+
+        let first = world.spawn((Counted(drops.clone()), Age(1)));
+        let _second = world.spawn((Counted(drops.clone()), Age(2)));
+
+        world.despawn(first);
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+
+        drop(world);
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn stale_handles_are_rejected() {
+        let mut world = World::default();
+
+        let old = world.spawn(Name("Old"));
+        world.despawn(old);
+        let new = world.spawn(Name("New"));
+
+        assert_eq!(old.index(), new.index());
+        assert_eq!(new.generation(), old.generation() + 1);
+
+        assert!(!world.contains(old));
+        assert_eq!(world.get::<Name>(old), None);
+        assert!(world.get_mut::<Name>(old).is_none());
+        assert!(!world.despawn(old));
+
+        assert_eq!(world.get::<Name>(new), Some(&Name("New")));
+    }
+
+    #[test]
+    fn exhausted_slots_are_retired() {
+        let mut world = World::default();
+
+        let old = world.spawn(Name("Old"));
+        world.generations[old.index() as usize] = u32::MAX;
+        let old = Entity::new(old.index(), u32::MAX);
+
+        assert!(world.contains(old));
+        assert!(world.despawn(old));
+
+        let new = world.spawn(Name("New"));
+
+        assert_ne!(old.index(), new.index());
+        assert!(!world.contains(old));
+        assert!(!world.despawn(old));
+    }
+
+    #[test]
+    fn entity_bits_and_formatting() {
+        let entity = Entity::new(12, 3);
+
+        assert_eq!(Entity::from_bits(entity.to_bits()), entity);
+        assert_eq!(entity.to_bits(), (3 << 32) | 12);
+        assert_eq!(format!("{entity}"), "12v3");
+        assert_eq!(format!("{entity:?}"), "12v3");
+    }
+
+    #[test]
+    fn insert_moves_entity_to_new_archetype() {
+        let mut world = World::default();
+
+        let entities = [
+            world.spawn((Name("John"), Age(18))),
+            world.spawn((Name("Hannah"), Age(24))),
+        ];
+
+        assert!(world.insert(entities[0], (Height(180.0), Age(19))));
+
+        assert_eq!(world.get::<Name>(entities[0]), Some(&Name("John")));
+        assert_eq!(world.get::<Age>(entities[0]), Some(&Age(19)));
+        assert_eq!(world.get::<Height>(entities[0]), Some(&Height(180.0)));
+        assert_eq!(world.get::<Name>(entities[1]), Some(&Name("Hannah")));
+
+        assert_eq!(
+            world
+                .query::<(EntityId, &Name, &Height)>()
+                .collect::<Vec<_>>(),
+            [(entities[0], &Name("John"), &Height(180.0))],
+        );
+
+        assert!(world.insert(entities[1], Age(25)));
+        assert_eq!(world.get::<Age>(entities[1]), Some(&Age(25)));
+
+        world.despawn(entities[1]);
+        assert!(!world.insert(entities[1], Age(0)));
+    }
+
+    #[test]
+    fn remove_moves_entity_to_new_archetype() {
+        let mut world = World::default();
+
+        let entities = [
+            world.spawn((Name("John"), Age(18), Speed(1.0))),
+            world.spawn((Name("Hannah"), Age(24), Speed(2.0))),
+        ];
+
+        assert_eq!(
+            world.remove::<(Speed, Age)>(entities[0]),
+            Some((Speed(1.0), Age(18)))
+        );
+        assert_eq!(world.remove::<Speed>(entities[0]), None);
+
+        assert_eq!(world.get::<Name>(entities[0]), Some(&Name("John")));
+        assert_eq!(world.get::<Age>(entities[0]), None);
+        assert_eq!(world.get::<Speed>(entities[1]), Some(&Speed(2.0)));
+
+        assert_eq!(world.remove::<Name>(entities[0]), Some(Name("John")));
+        assert!(world.contains(entities[0]));
+        assert!(world.insert(entities[0], Name("John")));
+        assert_eq!(world.get::<Name>(entities[0]), Some(&Name("John")));
+    }
+
+    #[test]
+    fn archetype_graph_caches_transitions() {
+        let mut world = World::default();
+
+        let first = world.spawn(Name("John"));
+        let second = world.spawn(Name("Hannah"));
+        let archetype_count = world.archetypes().len();
+
+        world.insert(first, Age(18));
+        let with_name = world.archetypes()[0].insert_edge::<Name>().unwrap();
+        let with_age = world.archetypes()[with_name].insert_edge::<Age>().unwrap();
+
+        assert_eq!(world.archetypes().len(), archetype_count + 1);
+        assert_eq!(
+            world.archetypes()[with_age].remove_edge::<Age>(),
+            Some(with_name)
+        );
+
+        world.insert(second, Age(24));
+        assert_eq!(world.archetypes().len(), archetype_count + 1);
+        assert_eq!(world.archetypes()[with_age].entities(), [first, second]);
+
+        assert_eq!(world.remove::<Age>(first), Some(Age(18)));
+        assert_eq!(world.archetypes()[with_name].entities(), [first]);
+        assert_eq!(world.archetypes().len(), archetype_count + 1);
+    }
+
+    #[test]
+    fn spawn_batch_reuses_archetype() {
+        let mut world = World::default();
+
+        let single = world.spawn((Name("Single"), Age(0)));
+        world.despawn(single);
+
+        let ids = world
+            .spawn_batch((0..100).map(|i| (Name("Batch"), Age(i))))
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids.len(), 100);
+        assert_eq!(ids[0].index(), single.index());
+        assert_eq!(world.archetypes().len(), 2);
+
+        for (i, &id) in ids.iter().enumerate() {
+            assert_eq!(world.get::<Age>(id), Some(&Age(i as u32)));
+        }
+
+        assert_eq!(world.query::<&Name>().count(), 100);
+    }
+
+    #[test]
+    fn optional_components() {
+        let mut world = World::default();
+
+        let entities = [
+            world.spawn((Name("John"), Age(18))),
+            world.spawn(Name("Hannah")),
+            world.spawn(Age(3)),
+        ];
+
+        assert_eq!(
+            world
+                .query::<(EntityId, &Name, Option<&Age>)>()
+                .collect::<Vec<_>>(),
+            [
+                (entities[0], &Name("John"), Some(&Age(18))),
+                (entities[1], &Name("Hannah"), None),
+            ],
+        );
+
+        for (age, has_name) in world.query_mut::<(Option<&mut Age>, Has<Name>)>() {
+            if let (Some(mut age), true) = (age, has_name) {
+                age.0 += 1;
+            }
+        }
+
+        assert_eq!(world.get::<Age>(entities[0]), Some(&Age(19)));
+        assert_eq!(world.get::<Age>(entities[2]), Some(&Age(3)));
+        assert_eq!(
+            world.query::<(Has<Tag>, Option<&Tag>)>().count(),
+            entities.len()
+        );
+    }
+
+    #[test]
+    fn with_without_filters() {
+        let mut world = World::default();
+
+        let entities = [
+            world.spawn((Name("John"), Tag)),
+            world.spawn((Name("Hannah"), Tag, Age(24))),
+            world.spawn(Name("Bob")),
+        ];
+
+        assert_eq!(
+            world
+                .query_filtered::<(EntityId, &Name), (With<Tag>, Without<Age>)>()
+                .collect::<Vec<_>>(),
+            [(entities[0], &Name("John"))],
+        );
+
+        for mut name in world.query_mut_filtered::<&mut Name, Without<Tag>>() {
+            name.0 = "Robert";
+        }
+
+        assert_eq!(world.get::<Name>(entities[2]), Some(&Name("Robert")));
+        assert_eq!(world.get::<Name>(entities[1]), Some(&Name("Hannah")));
+        assert_eq!(world.query_filtered::<&Name, With<Speed>>().count(), 0);
+    }
+
+    #[test]
+    fn or_and_any_of() {
+        let mut world = World::default();
+
+        let entities = [
+            world.spawn((Name("John"), Age(18))),
+            world.spawn((Name("Hannah"), Speed(2.0))),
+            world.spawn((Name("Bob"), Age(31), Speed(3.0))),
+            world.spawn(Name("Alice")),
+        ];
+
+        assert_eq!(
+            world
+                .query_filtered::<EntityId, Or<(With<Age>, With<Speed>)>>()
+                .collect::<Vec<_>>(),
+            [entities[0], entities[1], entities[2]],
+        );
+
+        assert_eq!(
+            world
+                .query_filtered::<EntityId, Or<(Without<Age>, With<Speed>)>>()
+                .collect::<Vec<_>>(),
+            [entities[1], entities[2], entities[3]],
+        );
+
+        for (age, speed) in world.query_mut::<AnyOf<(&mut Age, &mut Speed)>>() {
+            if let Some(mut age) = age {
+                age.0 += 1;
+            }
+
+            if let Some(mut speed) = speed {
+                speed.0 *= 2.0;
+            }
+        }
+
+        assert_eq!(
+            world
+                .query::<(&Name, AnyOf<(&Age, &Speed)>)>()
+                .collect::<Vec<_>>(),
+            [
+                (&Name("John"), (Some(&Age(19)), None)),
+                (&Name("Hannah"), (None, Some(&Speed(4.0)))),
+                (&Name("Bob"), (Some(&Age(32)), Some(&Speed(6.0)))),
+            ],
+        );
+    }
+
+    #[test]
+    fn query_mut_rejects_aliasing() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut world = World::default();
+        world.spawn((Name("John"), Age(18), Height(180.0), Speed(1.0), Tag));
+
+        macro_rules! assert_rejected {
+            ( $( $Q:ty ),+ $(,)? ) => {
+                $(
+                    let result = catch_unwind(AssertUnwindSafe(|| {
+                        world.query_mut::<$Q>().count();
+                    }));
+
+                    assert!(result.is_err(), "`{}` was accepted", stringify!($Q));
+                )+
+            };
+        }
+
+        assert_rejected!(
+            (&mut Name, &mut Name),
+            (&mut Name, &Age, &Name),
+            (&Name, &Age, &Height, &mut Name),
+            (&mut Age, &Name, &Height, &Speed, Option<&Age>),
+            (&Tag, &Name, &Height, &Speed, &Age, &mut Tag),
+            (&Name, &Age, &Height, &Speed, &Tag, EntityId, &mut Speed),
+            (
+                &Name,
+                &Age,
+                &Height,
+                &mut Speed,
+                &Tag,
+                EntityId,
+                Has<Age>,
+                &Speed
+            ),
+            (AnyOf<(&mut Age, &Name)>, &mut Age),
+        );
+
+        assert_eq!(world.query_mut::<(&Name, &Name, &mut Age)>().count(), 1);
+        assert_eq!(world.query_mut::<(&mut Name, Has<Name>)>().count(), 1);
+    }
+
+    #[test]
+    fn entity_get_many() {
+        let mut world = World::default();
+
+        let id = world.spawn((Name("John"), Age(18), Height(180.0)));
+        let mut entity = world.entity_mut(id);
+
+        let (mut age, name, mut height) =
+            entity.get_many::<(&mut Age, &Name, &mut Height)>().unwrap();
+        age.0 += 1;
+        height.0 += 1.0;
+        assert_eq!(name, &Name("John"));
+
+        assert_eq!(entity.get_many::<(&Age, &Speed)>(), None);
+        assert_eq!(
+            entity.get_many::<(&Age, Option<&Speed>)>(),
+            Some((&Age(19), None))
+        );
+        assert_eq!(entity.get::<Height>().as_deref(), Some(&Height(181.0)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn entity_get_many_rejects_aliasing() {
+        let mut world = World::default();
+
+        let id = world.spawn((Name("John"), Age(18)));
+        world.entity_mut(id).get_many::<(&mut Age, &Age)>();
+    }
+
+    #[test]
+    fn zero_sized_tags() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Marker;
+        impl Component for Marker {}
+
+        impl Drop for Marker {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut world = World::default();
+
+        let tag_only = world.spawn(Tag);
+        let mixed = world.spawn((Name("John"), Tag, Marker));
+
+        assert_eq!(world.entity(tag_only).get::<Tag>(), Some(&Tag));
+        assert!(world.entity(tag_only).has::<Tag>());
+        assert!(!world.entity(tag_only).has::<Name>());
+        assert!(world.get::<Marker>(mixed).is_some());
+        assert_eq!(world.get_mut::<Tag>(mixed).as_deref(), Some(&Tag));
+
+        assert_eq!(
+            world.query::<(EntityId, &Tag)>().collect::<Vec<_>>(),
+            [(tag_only, &Tag), (mixed, &Tag)],
+        );
+
+        world.insert(tag_only, Marker);
+        assert!(world.remove::<Marker>(tag_only).is_some());
+        assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+
+        world.despawn(mixed);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+
+        world.insert(tag_only, Marker);
+        drop(world);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn fallible_api() {
+        let mut world = World::default();
+
+        let id = world.try_spawn((Name("John"), Age(18))).unwrap();
+
+        assert_eq!(world.try_get::<Name>(id), Ok(&Name("John")));
+        assert_eq!(
+            world.try_get_mut::<Height>(id).err(),
+            Some(TecsError::MissingComponent {
+                entity: id,
+                component: std::any::type_name::<Height>(),
+            }),
+        );
+
+        assert_eq!(
+            world.try_reserve::<(Name, Age)>(usize::MAX),
+            Err(TecsError::CapacityOverflow),
+        );
+        assert_eq!(
+            world.try_reserve::<(Name, Age)>(usize::MAX / 2),
+            Err(TecsError::CapacityOverflow),
+        );
+        assert_eq!(world.try_reserve::<(Name, Age)>(16), Ok(()));
+
+        let other = world.spawn((Name("Hannah"), Age(24)));
+        assert_eq!(world.get::<Name>(id), Some(&Name("John")));
+        assert_eq!(world.get::<Age>(other), Some(&Age(24)));
+
+        world.despawn(id);
+
+        assert!(world.try_entity(id).is_err());
+        assert!(world.try_entity_mut(id).is_err());
+        assert_eq!(world.try_get::<Name>(id), Err(TecsError::NoSuchEntity(id)));
+        assert_eq!(
+            TecsError::NoSuchEntity(id).to_string(),
+            format!("entity {id} does not exist"),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "more than once")]
+    fn duplicate_components_in_spawn() {
+        let mut world = World::default();
+        world.spawn((Age(1), Name("John"), Age(2)));
+    }
+
+    #[test]
+    fn duplicate_components_are_not_written() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted;
+        impl Component for Counted {}
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut world = World::default();
+        let id = world.spawn(Name("John"));
+
+        for _ in 0..2 {
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                world.spawn((Counted, Counted));
+            }));
+            assert!(result.is_err());
+
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                world.insert(id, (Counted, Age(1), Counted));
+            }));
+            assert!(result.is_err());
+        }
+
+        assert_eq!(DROPS.load(Ordering::Relaxed), 8);
+        assert_eq!(world.get::<Name>(id), Some(&Name("John")));
+        assert_eq!(world.query::<&Age>().count(), 0);
+
+        drop(world);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 8);
+    }
+
+    #[test]
+    fn world_is_send_and_sync() {
+        use std::sync::{Arc, RwLock};
+
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<World>();
+
+        let mut world = World::default();
+        let id = world.spawn((Name("John"), Age(18)));
+
+        let world = std::thread::spawn(move || {
+            world.get_mut::<Age>(id).unwrap().0 += 1;
+            world
+        })
+        .join()
+        .unwrap();
+
+        let world = Arc::new(RwLock::new(world));
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let world = world.read().unwrap();
+                    assert_eq!(world.get::<Age>(id), Some(&Age(19)));
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn local_world_stores_non_send_components() {
+        use std::{cell::Cell, rc::Rc};
+
+        let mut world = LocalWorld::new();
+        let shared = Rc::new(Cell::new(0));
+
+        let id = world.spawn(Name("Window"));
+        let other = world.spawn(Name("Cursor"));
+        assert!(world.insert_non_send(id, shared.clone()));
+        assert!(world.insert_non_send(other, shared.clone()));
+        assert_eq!(Rc::strong_count(&shared), 3);
+
+        world.get_non_send_mut::<Rc<Cell<i32>>>(id).unwrap().set(42);
+        assert_eq!(shared.get(), 42);
+        assert_eq!(world.get_non_send::<Rc<i32>>(id), None);
+
+        // the wrapped world is shared with the non-send components
+        assert_eq!(world.query::<&Name>().count(), 2);
+        assert!(world.insert(id, Age(1)));
+
+        // despawning drops the non-send components, and stale ids do not reach them
+        assert!(world.despawn(id));
+        assert_eq!(Rc::strong_count(&shared), 2);
+        assert!(!world.insert_non_send(id, Rc::new(0)));
+        assert_eq!(world.get_non_send::<Rc<Cell<i32>>>(id), None);
+
+        let removed = world.remove_non_send::<Rc<Cell<i32>>>(other);
+        assert!(removed.is_some_and(|removed| Rc::ptr_eq(&removed, &shared)));
+        assert_eq!(Rc::strong_count(&shared), 1);
+
+        world.insert_non_send(other, shared.clone());
+
+        let world = world.into_world();
+        assert_eq!(Rc::strong_count(&shared), 1);
+        assert_eq!(world.get::<Name>(other), Some(&Name("Cursor")));
+    }
+
+    #[test]
+    fn resources() {
+        #[derive(Debug, PartialEq)]
+        struct DeltaTime(f32);
+        impl Resource for DeltaTime {}
+
+        struct Config(std::sync::Arc<()>);
+        impl Resource for Config {}
+
+        let mut world = World::default();
+
+        assert!(!world.contains_resource::<DeltaTime>());
+        assert_eq!(
+            world.try_resource::<DeltaTime>().err(),
+            Some(TecsError::MissingResource {
+                resource: std::any::type_name::<DeltaTime>(),
+            }),
+        );
+
+        assert_eq!(world.insert_resource(DeltaTime(0.5)), None);
+        world.resource_mut::<DeltaTime>().0 += 0.25;
+        assert_eq!(world.resource::<DeltaTime>(), &DeltaTime(0.75));
+        assert_eq!(world.remove_resource::<DeltaTime>(), Some(DeltaTime(0.75)));
+        assert_eq!(world.remove_resource::<DeltaTime>(), None);
+
+        let config = std::sync::Arc::new(());
+        world.insert_resource(Config(config.clone()));
+        assert!(std::sync::Arc::ptr_eq(
+            &world.resource::<Config>().0,
+            &config
+        ));
+
+        drop(world);
+        assert_eq!(std::sync::Arc::strong_count(&config), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not exist")]
+    fn missing_resource_panics() {
+        struct Score;
+        impl Resource for Score {}
+
+        World::default().resource::<Score>();
+    }
+
+    #[test]
+    fn command_buffer() {
+        let mut world = World::default();
+
+        let john = world.spawn((Name("John"), Age(18)));
+        let hannah = world.spawn((Name("Hannah"), Age(24)));
+        let stale = world.spawn(Name("Ghost"));
+        world.despawn(stale);
+
+        let mut commands = CommandBuffer::new();
+
+        for (id, mut age) in world.query_mut::<(EntityId, &mut Age)>() {
+            age.0 += 1;
+
+            if age.0 > 20 {
+                commands.remove::<Age>(id);
+                commands.insert(id, Tag);
+            }
+        }
+
+        let child = commands.spawn(Name("Alice"));
+        commands.insert(child, (Age(1), Height(0.5)));
+        let temporary = commands.spawn(Tag);
+        commands.despawn(temporary);
+        commands.insert(stale, Age(0));
+        commands.despawn(john);
+
+        assert_eq!(commands.len(), 8);
+
+        world.apply(&mut commands);
+
+        assert!(commands.is_empty());
+        assert!(!world.contains(john));
+        assert!(!world.contains(stale));
+        assert_eq!(world.get::<Age>(hannah), None);
+        assert_eq!(world.get::<Tag>(hannah), Some(&Tag));
+
+        let spawned: Vec<_> = world
+            .query::<(&Name, &Age, &Height)>()
+            .map(|(name, age, _)| (name.0, age.0))
+            .collect();
+        assert_eq!(spawned, [("Alice", 1)]);
+        assert_eq!(world.query::<&Tag>().count(), 1);
+
+        // handles of other buffers and of applied commands are skipped
+        let mut other = CommandBuffer::new();
+        let foreign = other.spawn(Name("Foreign"));
+        let bob = commands.spawn(Name("Bob"));
+        commands.insert(foreign, Age(5));
+        commands.insert(child, Age(6));
+        commands.insert(bob, Age(7));
+        assert_eq!(CommandEntity::from(john).existing(), Some(john));
+        assert_eq!(bob.existing(), None);
+
+        world.apply(&mut commands);
+
+        let mut ages: Vec<_> = world
+            .query::<(&Name, &Age)>()
+            .map(|(name, age)| (name.0, age.0))
+            .collect();
+        ages.sort_unstable();
+        assert_eq!(ages, [("Alice", 1), ("Bob", 7)]);
+    }
+
+    #[test]
+    fn reserved_entities() {
+        let mut world = World::default();
+
+        let alive = world.spawn(Name("John"));
+        let freed = [world.spawn(Age(1)), world.spawn(Age(2))];
+        for id in freed {
+            world.despawn(id);
+        }
+
+        let reserved: Vec<_> = world.reserve_entities(4).collect();
+        let single = world.reserve_entity();
+
+        assert_eq!(reserved.len(), 4);
+        assert!(reserved.iter().all(|&id| !world.contains(id)));
+        assert_eq!(
+            reserved[..2]
+                .iter()
+                .map(|id| id.index())
+                .collect::<std::collections::HashSet<_>>(),
+            freed.iter().map(|id| id.index()).collect(),
+        );
+        assert!(reserved[..2].iter().all(|id| id.generation() == 1));
+
+        world.insert(reserved[0], Height(1.0));
+
+        for &id in reserved.iter().chain([&single]) {
+            assert!(world.contains(id));
+        }
+        assert_eq!(world.get::<Height>(reserved[0]), Some(&Height(1.0)));
+        assert_eq!(world.archetypes()[0].len(), 4);
+
+        let spawned = world.spawn(Name("Hannah"));
+        assert!(!reserved.contains(&spawned) && spawned != single);
+        assert_eq!(world.get::<Name>(alive), Some(&Name("John")));
+
+        assert!(world.despawn(single));
+        assert_eq!(world.reserve_entity().index(), single.index());
+    }
+
+    #[test]
+    fn change_detection() {
+        let mut world = World::default();
+
+        let john = world.spawn((Name("John"), Age(18)));
+        let hannah = world.spawn((Name("Hannah"), Age(24)));
+        let bob = world.spawn((Name("Bob"), Age(30)));
+
+        let added = |world: &World| -> Vec<_> {
+            world
+                .query_filtered::<&Name, Added<Age>>()
+                .map(|name| name.0)
+                .collect()
+        };
+        let changed = |world: &World| -> Vec<_> {
+            world
+                .query_filtered::<&Name, Changed<Age>>()
+                .map(|name| name.0)
+                .collect()
+        };
+
+        assert_eq!(added(&world), ["John", "Hannah", "Bob"]);
+        world.clear_trackers();
+        assert!(added(&world).is_empty() && changed(&world).is_empty());
+
+        for (name, mut age) in world.query_mut::<(&Name, &mut Age)>() {
+            if name.0 == "Hannah" {
+                age.0 += 1;
+            } else {
+                assert!(age.0 < 100);
+            }
+        }
+
+        world
+            .get_mut::<Age>(bob)
+            .unwrap()
+            .bypass_change_detection()
+            .0 += 1;
+        assert_eq!(changed(&world), ["Hannah"]);
+
+        // moving entities between archetypes keeps their ticks
+        world.despawn(john);
+        world.insert(hannah, Tag);
+        world.insert(bob, Age(0));
+
+        assert_eq!(added(&world), ["Bob"]);
+        assert_eq!(changed(&world), ["Bob", "Hannah"]);
+        assert_eq!(
+            world
+                .query_filtered::<&Name, Or<(Added<Age>, With<Tag>)>>()
+                .count(),
+            2,
+        );
+
+        let tick = world.change_tick();
+        world.clear_trackers();
+
+        assert_eq!(world.last_change_tick(), tick);
+        assert!(changed(&world).is_empty());
+
+        world.entity_mut(hannah).get::<Age>().unwrap().set_changed();
+        assert_eq!(changed(&world), ["Hannah"]);
+        assert_eq!(
+            world.get_mut::<Age>(hannah).unwrap().changed_tick(),
+            world.change_tick(),
+        );
+    }
+
+    #[test]
+    fn removal_tracking() {
+        let mut world = World::default();
+
+        let john = world.spawn((Name("John"), Age(18)));
+        let hannah = world.spawn((Name("Hannah"), Age(24)));
+
+        world.remove::<Age>(john);
+        let first_frame = world.change_tick();
+        world.clear_trackers();
+
+        world.despawn(hannah);
+        assert_eq!(world.remove::<Age>(john), None);
+
+        assert_eq!(world.removed::<Age>().collect::<Vec<_>>(), [hannah]);
+        assert_eq!(world.removed::<Name>().collect::<Vec<_>>(), [hannah]);
+        assert_eq!(
+            world.removed_since::<Age>(0).collect::<Vec<_>>(),
+            [john, hannah],
+        );
+
+        // removals of the previous frame are still retained
+        world.clear_trackers();
+        assert_eq!(world.removed_since::<Age>(0).collect::<Vec<_>>(), [hannah]);
+        world.clear_trackers();
+        assert_eq!(world.removed_since::<Age>(0).count(), 0);
+
+        world.set_removal_retention(RemovalRetention::Manual);
+        world.remove::<Name>(john);
+
+        for _ in 0..4 {
+            world.clear_trackers();
+        }
+
+        assert_eq!(
+            world.removed_since::<Name>(first_frame).collect::<Vec<_>>(),
+            [john],
+        );
+
+        world.clear_removed();
+        assert_eq!(world.removed_since::<Name>(0).count(), 0);
+    }
+
+    #[test]
+    fn event_queues() {
+        #[derive(Debug, PartialEq)]
+        struct Hit(u32);
+        impl Event for Hit {}
+
+        let mut world = World::default();
+        world.add_event::<Hit>();
+        world.add_event::<Hit>();
+
+        let mut fast = EventCursor::new();
+        let mut slow = EventCursor::new();
+
+        world.send_event(Hit(1));
+        world.send_event(Hit(2));
+
+        let events = world.resource::<Events<Hit>>();
+        assert_eq!(fast.len(events), 2);
+        assert_eq!(fast.read(events).collect::<Vec<_>>(), [&Hit(1), &Hit(2)]);
+        assert!(fast.is_empty(events));
+
+        world.update_events();
+        world.send_event(Hit(3));
+
+        let events = world.resource::<Events<Hit>>();
+        assert_eq!(fast.read(events).collect::<Vec<_>>(), [&Hit(3)]);
+        assert_eq!(events.len(), 3);
+
+        // the first two events are dropped by the second update since they were sent
+        world.update_events();
+
+        let mut reader = slow.reader(world.resource::<Events<Hit>>());
+        assert_eq!(reader.read().collect::<Vec<_>>(), [&Hit(3)]);
+        assert_eq!(reader.read().count(), 0);
+
+        world.update_events();
+        world.send_event(Hit(4));
+        world.resource_mut::<Events<Hit>>().clear();
+
+        let events = world.resource::<Events<Hit>>();
+        assert!(events.is_empty());
+        assert!(fast.is_empty(events) && slow.is_empty(events));
+        assert_eq!(EventCursor::new().read(events).count(), 0);
+
+        // removed queues are skipped and re-added ones are updated once per call
+        world.remove_resource::<Events<Hit>>();
+        world.update_events();
+
+        world.add_event::<Hit>();
+        world.send_event(Hit(5));
+        world.update_events();
+        assert_eq!(world.resource::<Events<Hit>>().len(), 1);
+
+        world.update_events();
+        assert!(world.resource::<Events<Hit>>().is_empty());
+    }
+
+    #[test]
+    fn function_systems() {
+        #[derive(Default)]
+        struct Log(Vec<&'static str>);
+        impl Resource for Log {}
+
+        struct Total(u32);
+        impl Resource for Total {}
+
+        struct Birthday(u32);
+        impl Event for Birthday {}
+
+        fn grow_up(mut people: Query<(&Name, &mut Age)>) {
+            for (name, mut age) in &mut people {
+                if name.0 != "Hannah" {
+                    age.0 += 1;
+                }
+            }
+        }
+
+        fn congratulate(
+            people: Query<&Name, Changed<Age>>,
+            mut log: ResMut<Log>,
+            mut commands: Commands,
+        ) {
+            for name in &people {
+                log.0.push(name.0);
+                commands.spawn(Age(0));
+            }
+        }
+
+        fn count_birthdays(mut birthdays: EventReader<Birthday>, mut total: ResMut<Total>) {
+            total.0 += birthdays.read().map(|event| event.0).sum::<u32>();
+        }
+
+        let mut world = World::default();
+        world.insert_resource(Log::default());
+        world.insert_resource(Total(0));
+        world.add_event::<Birthday>();
+
+        world.spawn((Name("John"), Age(18)));
+        world.spawn((Name("Hannah"), Age(24)));
+        world.spawn((Name("Bob"), Age(30)));
+
+        let mut grow_up = grow_up.into_system();
+        let mut congratulate = congratulate.into_system();
+
+        // every component is new for the first run
+        grow_up.run(&mut world);
+        congratulate.run(&mut world);
+        assert_eq!(world.resource::<Log>().0, ["John", "Hannah", "Bob"]);
+
+        grow_up.run(&mut world);
+        congratulate.run(&mut world);
+        assert_eq!(world.resource::<Log>().0[3..], ["John", "Bob"]);
+        assert_eq!(world.query::<&Age>().count(), 8);
+
+        assert!(!congratulate.access().is_compatible(grow_up.access()));
+
+        let mut count_birthdays = count_birthdays.into_system();
+        world.send_event(Birthday(19));
+
+        count_birthdays.run(&mut world);
+        world.send_event(Birthday(31));
+        count_birthdays.run(&mut world);
+        count_birthdays.run(&mut world);
+        assert_eq!(world.resource::<Total>().0, 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicts with its other parameters")]
+    fn conflicting_system_params() {
+        fn rename(_names: Query<&mut Name>, _people: Query<(&Name, &Age)>) {}
+
+        rename.into_system().initialize(&mut World::default());
+    }
+
+    #[test]
+    fn schedules() {
+        #[derive(Default)]
+        struct Counts(Vec<usize>);
+        impl Resource for Counts {}
+
+        fn spawn(mut commands: Commands) {
+            commands.spawn(Age(0));
+        }
+
+        fn count(ages: Query<&Age>, mut counts: ResMut<Counts>) {
+            counts.0.push(ages.iter().count());
+        }
+
+        let mut world = World::default();
+        world.insert_resource(Counts::default());
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(count.in_set("report"))
+            .add_system(count.after("spawn"))
+            .add_system(spawn.label("spawn"));
+        schedule.configure_set("report").before("spawn");
+
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+
+        // spawned entities are visible right after the sync point and the schedule is flushed
+        // between runs
+        assert_eq!(world.resource::<Counts>().0, [0, 1, 1, 2]);
+        assert_eq!(schedule.len(), 3);
+
+        schedule.add_system(spawn.label("spawn"));
+        assert_eq!(
+            schedule.initialize(&mut world),
+            Err(TecsError::DuplicateLabel {
+                label: "spawn".into()
+            }),
+        );
+    }
+
+    #[test]
+    fn schedule_cycles() {
+        fn a() {}
+        fn b() {}
+        fn c() {}
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(a.label("a").before("middle"))
+            .add_system(b.in_set("middle"))
+            .add_system(c.label("c").before("a"));
+        schedule.configure_set("middle").before("c");
+
+        let Err(TecsError::ScheduleCycle { systems }) = schedule.initialize(&mut World::new())
+        else {
+            panic!("the cycle is not detected");
+        };
+
+        let names: Vec<_> = systems
+            .iter()
+            .map(|name| name.rsplit("::").next())
+            .collect();
+        assert_eq!(names, [Some("b"), Some("c"), Some("a")]);
+
+        let message = TecsError::ScheduleCycle { systems }.to_string();
+        assert!(message.ends_with("::a` -> `tecs::tests::schedule_cycles::b`"));
+    }
+
+    #[test]
+    fn schedule_unknown_labels() {
+        fn a() {}
+        fn b() {}
+
+        let mut world = World::new();
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(a.label("a").in_set("first"))
+            .add_system(b.after("frist"));
+
+        assert_eq!(
+            schedule.initialize(&mut world),
+            Err(TecsError::UnknownLabel {
+                label: "frist".to_owned(),
+            }),
+        );
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(a.label("a")).add_system(b.after("a"));
+        schedule.configure_set("last").after("a");
+
+        let error = schedule.initialize(&mut world).unwrap_err();
+        assert_eq!(error.to_string(), "no system has label or is in set `last`");
+
+        schedule.add_system(b.in_set("last"));
+        assert_eq!(schedule.initialize(&mut world), Ok(()));
+    }
+
+    #[test]
+    fn parallel_executor() {
+        use std::{
+            collections::HashSet,
+            sync::{Barrier, Mutex},
+            thread::{self, ThreadId},
+        };
+
+        /// Makes the systems wait for each other unless there is no barrier.
+        struct Meeting {
+            barrier: Option<Barrier>,
+            threads: Mutex<HashSet<ThreadId>>,
+        }
+        impl Resource for Meeting {}
+
+        impl Meeting {
+            fn new(barrier: Option<Barrier>) -> Self {
+                Self {
+                    barrier,
+                    threads: Mutex::default(),
+                }
+            }
+
+            fn attend(&self) {
+                self.threads.lock().unwrap().insert(thread::current().id());
+
+                if let Some(barrier) = &self.barrier {
+                    barrier.wait();
+                }
+            }
+        }
+
+        #[derive(Default)]
+        struct Log(Mutex<Vec<&'static str>>);
+        impl Resource for Log {}
+
+        fn age(mut ages: Query<&mut Age>, meeting: Res<Meeting>, log: Res<Log>) {
+            meeting.attend();
+
+            for mut age in &mut ages {
+                age.0 += 1;
+            }
+
+            log.0.lock().unwrap().push("age");
+        }
+
+        fn rename(mut names: Query<&mut Name>, meeting: Res<Meeting>, log: Res<Log>) {
+            meeting.attend();
+
+            for mut name in &mut names {
+                name.0 = "Johnny";
+            }
+
+            log.0.lock().unwrap().push("rename");
+        }
+
+        let mut world = World::default();
+        let id = world.spawn((Name("John"), Age(18)));
+        world.insert_resource(Meeting::new(Some(Barrier::new(2))));
+        world.insert_resource(Log::default());
+
+        let mut schedule = Schedule::new();
+        schedule
+            .set_thread_count(2)
+            .add_system(rename)
+            .add_system(age);
+
+        // the systems access different components, so they run at the same time and both pass
+        // the barrier
+        assert_eq!(schedule.executor(), ExecutorKind::MultiThreaded);
+        schedule.run(&mut world);
+
+        assert_eq!(world.resource::<Log>().0.lock().unwrap().len(), 2);
+        assert_eq!(world.get::<Age>(id), Some(&Age(19)));
+        assert_eq!(world.get::<Name>(id), Some(&Name("Johnny")));
+
+        // the schedule keeps its worker thread between runs
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<Meeting>().threads.lock().unwrap().len(), 2);
+
+        world.insert_resource(Meeting::new(None));
+        world.insert_resource(Log::default());
+        schedule
+            .set_executor(ExecutorKind::SingleThreaded)
+            .run(&mut world);
+
+        // a single thread runs the systems in the order they were added
+        assert_eq!(
+            *world.resource::<Log>().0.lock().unwrap(),
+            ["rename", "age"]
+        );
+        assert_eq!(world.get::<Age>(id), Some(&Age(21)));
+        assert_eq!(world.resource::<Meeting>().threads.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn parallel_executor_respects_conflicts_and_ordering() {
+        use std::sync::{Barrier, Mutex};
+
+        /// Lets `grow_up` wait until `greet` runs next to it.
+        struct Handshake(Barrier);
+        impl Resource for Handshake {}
+
+        #[derive(Default)]
+        struct Log(Mutex<Vec<&'static str>>);
+        impl Resource for Log {}
+
+        fn spawn(mut commands: Commands, log: Res<Log>) {
+            log.0.lock().unwrap().push("spawn");
+            commands.spawn((Name("Hannah"), Age(24)));
+        }
+
+        fn grow_up(mut ages: Query<&mut Age>, handshake: Res<Handshake>, log: Res<Log>) {
+            log.0.lock().unwrap().push("grow up start");
+            handshake.0.wait();
+
+            for mut age in &mut ages {
+                age.0 += 1;
+            }
+
+            log.0.lock().unwrap().push("grow up end");
+        }
+
+        fn count(ages: Query<&Age>, log: Res<Log>) {
+            log.0.lock().unwrap().push(if ages.iter().count() == 2 {
+                "count"
+            } else {
+                "count too early"
+            });
+        }
+
+        fn greet(names: Query<&Name>, handshake: Res<Handshake>, log: Res<Log>) {
+            assert_eq!(names.iter().count(), 2);
+            handshake.0.wait();
+            log.0.lock().unwrap().push("greet");
+        }
+
+        let mut world = World::default();
+        world.spawn((Name("John"), Age(18)));
+        world.insert_resource(Handshake(Barrier::new(2)));
+        world.insert_resource(Log::default());
+
+        let mut schedule = Schedule::new();
+        schedule
+            .set_thread_count(4)
+            .add_system(grow_up.after("spawn"))
+            .add_system(count.after("spawn"))
+            .add_system(greet.after("spawn"))
+            .add_system(spawn.label("spawn"));
+
+        schedule.run(&mut world);
+
+        // `count` conflicts with `grow_up`, so it waits for it to end while `greet`, which comes
+        // later but is compatible, runs next to it
+        let log = world.resource::<Log>().0.lock().unwrap().clone();
+        let position = |entry| log.iter().position(|&other| other == entry).unwrap();
+
+        assert_eq!(log.len(), 5);
+        assert_eq!(log[..2], ["spawn", "grow up start"]);
+        assert!(position("grow up end") < position("count"));
+        assert!(position("grow up start") < position("greet"));
+
+        let mut ages: Vec<_> = world.query::<&Age>().map(|age| age.0).collect();
+        ages.sort_unstable();
+        assert_eq!(ages, [19, 25]);
+    }
+
+    #[test]
+    fn parallel_executor_propagates_panics() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        fn fail(_ages: Query<&Age>) {
+            panic!("system failed");
+        }
+
+        fn grow_up(mut ages: Query<&mut Age>) {
+            for mut age in &mut ages {
+                age.0 += 1;
+            }
+        }
+
+        let mut world = World::default();
+        let id = world.spawn((Name("John"), Age(18)));
+
+        let mut schedule = Schedule::new();
+        schedule
+            .set_thread_count(2)
+            .add_system(fail.label("fail"))
+            .add_system(grow_up.after("fail"))
+            .add_system(|| {});
+
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| schedule.run(&mut world)))
+            .expect_err("the panic reaches the caller");
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"system failed"));
+
+        // systems ordered after the failed one do not start, and the workers stop on drop
+        assert_eq!(world.get::<Age>(id), Some(&Age(18)));
+        drop(schedule);
+    }
+
+    #[test]
+    fn parallel_queries() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        fn age_everyone(mut people: Query<(&Name, &mut Age)>) {
+            people.par_for_each_mut(|(name, mut age)| {
+                if name.0 != "Hannah" {
+                    age.0 += 1;
+                }
+            });
+        }
+
+        let mut world = World::default();
+        world
+            .spawn_batch((0..5000).map(|i| (Name("John"), Age(i))))
+            .for_each(drop);
+        world
+            .spawn_batch((0..3000).map(|i| (Name("Hannah"), Age(i), Height(1.7))))
+            .for_each(drop);
+        world
+            .spawn_batch((0..10).map(|i| (Name("Bob"), Age(i), Speed(0.0))))
+            .for_each(drop);
+        world.clear_trackers();
+
+        age_everyone.into_system().run(&mut world);
+
+        let sum = AtomicU64::new(0);
+        world
+            .query_filtered::<&Age, Changed<Age>>()
+            .par_for_each(|age| {
+                sum.fetch_add(age.0 as u64, Ordering::Relaxed);
+            });
+        assert_eq!(
+            sum.into_inner(),
+            (1..=5000).sum::<u64>() + (1..=10).sum::<u64>()
+        );
+
+        // a partially consumed iterator only processes the remaining items
+        let mut query = world.query_mut::<&mut Age>();
+        query.by_ref().take(100).for_each(|mut age| age.0 = 0);
+        query.par_for_each(|mut age| age.0 = 1);
+
+        let mut ages = [0; 2];
+        for age in world.query::<&Age>() {
+            ages[age.0 as usize] += 1;
+        }
+        assert_eq!(ages, [100, 7910]);
+    }
+
+    #[test]
+    fn query_chunks() {
+        let mut world = World::default();
+
+        let john = world.spawn((Name("John"), Age(18)));
+        let hannah = world.spawn((Name("Hannah"), Age(24)));
+        let bob = world.spawn((Name("Bob"), Age(30), Speed(1.5)));
+        world.spawn(Name("Unknown"));
+        world.clear_trackers();
+
+        let chunks: Vec<_> = world
+            .query_chunks_mut::<(&Name, &mut Age, Option<&Speed>)>()
+            .map(|(ids, names, ages, speeds)| {
+                assert_eq!(ids.len(), names.len());
+                ages.iter_mut().for_each(|age| age.0 += 1);
+
+                let names: Vec<_> = names.iter().map(|name| name.0).collect();
+                (ids.to_vec(), names, speeds.map(<[Speed]>::len))
+            })
+            .collect();
+
+        assert_eq!(
+            chunks,
+            [
+                (vec![john, hannah], vec!["John", "Hannah"], None),
+                (vec![bob], vec!["Bob"], Some(1)),
+            ],
+        );
+        assert_eq!(world.get::<Age>(bob), Some(&Age(31)));
+        assert_eq!(world.query_filtered::<&Age, Changed<Age>>().count(), 3);
+
+        let ids: Vec<_> = world
+            .query_chunks_filtered::<&Name, (With<Age>, Without<Speed>)>()
+            .flat_map(|(ids, _)| ids)
+            .copied()
+            .collect();
+        assert_eq!(ids, [john, hannah]);
+
+        assert_eq!(world.query_chunks::<&Height>().count(), 0);
+    }
 }
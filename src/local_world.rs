@@ -0,0 +1,153 @@
+use crate::{EntityId, World};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+/// A [`World`] that can also store components which are not [`Send`] or [`Sync`], e.g. `Rc` or
+/// raw handles of a window system. Unlike [`World`] it is neither `Send` nor `Sync`, which pins it
+/// to the thread that created it, so the non-send components are only ever accessed and dropped
+/// on that thread.
+///
+/// The wrapped world is reachable through [`Deref`] and [`DerefMut`]. Non-send components are
+/// stored next to it, keyed by entity, and are not visible to queries. Entities should be
+/// despawned through [`LocalWorld::despawn`] to drop their non-send components right away,
+/// otherwise the components are dropped together with the local world.
+///
+/// # Example
+///
+/// ```rust
+/// use std::rc::Rc;
+/// use tecs::{LocalWorld, Component};
+///
+/// struct Name(&'static str);
+/// impl Component for Name {}
+///
+/// let mut world = LocalWorld::new();
+///
+/// let id = world.spawn(Name("Window"));
+/// assert!(world.insert_non_send(id, Rc::new(42)));
+///
+/// assert_eq!(world.get_non_send::<Rc<i32>>(id), Some(&Rc::new(42)));
+/// assert_eq!(world.get::<Name>(id).unwrap().0, "Window");
+/// ```
+///
+/// The local world cannot leave its thread.
+///
+/// ```rust,compile_fail
+/// use tecs::LocalWorld;
+///
+/// let world = LocalWorld::new();
+///
+/// std::thread::spawn(move || drop(world));
+/// ```
+#[derive(Default)]
+pub struct LocalWorld {
+    world: World,
+    non_send: HashMap<EntityId, HashMap<TypeId, Box<dyn Any>>>,
+    /// Opts out of `Send` and `Sync`.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl LocalWorld {
+    /// Constructs new empty local world.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops the non-send components returning the wrapped world.
+    pub fn into_world(self) -> World {
+        self.world
+    }
+
+    /// Adds a non-send component to the entity replacing the previous one of the same type.
+    /// Returns `false` if the entity does not exist.
+    pub fn insert_non_send<T: 'static>(&mut self, id: EntityId, value: T) -> bool {
+        if !self.world.contains(id) {
+            return false;
+        }
+
+        self.non_send
+            .entry(id)
+            .or_default()
+            .insert(TypeId::of::<T>(), Box::new(value));
+
+        true
+    }
+
+    /// Removes a non-send component from the entity. Returns `None` if the entity does not exist
+    /// or does not have the component.
+    pub fn remove_non_send<T: 'static>(&mut self, id: EntityId) -> Option<T> {
+        if !self.world.contains(id) {
+            return None;
+        }
+
+        let value = self.non_send.get_mut(&id)?.remove(&TypeId::of::<T>())?;
+
+        value.downcast::<T>().ok().map(|value| *value)
+    }
+
+    /// Retrieves a non-send component of the entity. Returns `None` if the entity does not exist
+    /// or does not have the component.
+    pub fn get_non_send<T: 'static>(&self, id: EntityId) -> Option<&T> {
+        if !self.world.contains(id) {
+            return None;
+        }
+
+        self.non_send
+            .get(&id)?
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+
+    /// Retrieves a non-send component of the entity mutably. Returns `None` if the entity does
+    /// not exist or does not have the component.
+    pub fn get_non_send_mut<T: 'static>(&mut self, id: EntityId) -> Option<&mut T> {
+        if !self.world.contains(id) {
+            return None;
+        }
+
+        self.non_send
+            .get_mut(&id)?
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut::<T>())
+    }
+
+    /// Despawns the entity dropping both its components and its non-send components, see
+    /// [`World::despawn`].
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        if !self.world.despawn(id) {
+            return false;
+        }
+
+        self.non_send.remove(&id);
+
+        true
+    }
+}
+
+impl From<World> for LocalWorld {
+    fn from(world: World) -> Self {
+        Self {
+            world,
+            non_send: HashMap::new(),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Deref for LocalWorld {
+    type Target = World;
+
+    fn deref(&self) -> &Self::Target {
+        &self.world
+    }
+}
+
+impl DerefMut for LocalWorld {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.world
+    }
+}
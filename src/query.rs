@@ -1,285 +1,855 @@
-use core::slice;
-use std::any::TypeId;
+use std::{
+    any::{type_name, TypeId},
+    cell::{RefCell, UnsafeCell},
+    collections::HashSet,
+    marker::PhantomData,
+    ops::Range,
+    slice,
+};
+
+use crate::{
+    access::Access,
+    archetype::Archetype,
+    change::{ChangeTicks, ComponentTicks, Mut, Tick},
+    Component, EntityId,
+};
+
+/// A part of a query that can be fetched from every row of matching archetypes. Implemented for
+/// [`EntityId`], `&T`, `&mut T`, [`Option`], [`Has`], [`AnyOf`] and tuples of them. `&mut T`
+/// fetches [`Mut<T>`](Mut) to track changes of the component.
+///
+/// # Safety
+///
+/// - `prepare` should be called only on archetypes `matches` returned `true` for.
+/// - `fetch` should only access the row `entity_index` of the prepared archetype.
+/// - `access` should record every component `fetch` reads or writes.
+pub unsafe trait Fetch {
+    /// The value fetched from a single row.
+    type Item<'w>;
+
+    /// Per-archetype state used to fetch single rows.
+    type State: Copy;
+
+    /// Checks if the archetype can be fetched from.
+    fn matches(archetype: &Archetype) -> bool;
+
+    /// Records the components this fetch reads and writes.
+    fn access(access: &mut Access);
+
+    /// Prepares the state for the archetype. Nothing is marked changed here: fetches of `&mut T`
+    /// keep `ticks.this_run`, and the [`Mut`] items they yield mark the component changed at that
+    /// tick once it is accessed mutably.
+    fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State;
+
+    /// Fetches the row `entity_index` of the prepared archetype.
+    ///
+    /// # Safety
+    ///
+    /// - `entity_index` should be less than the length of the prepared archetype.
+    /// - the returned item should not outlive the archetype and alias other mutable borrows.
+    unsafe fn fetch<'w>(state: Self::State, entity_index: usize) -> Self::Item<'w>;
+}
+
+/// A [`Fetch`] that only reads components. Only such fetches can be used with
+/// [`World::query`](crate::World::query).
+///
+/// # Safety
+///
+/// `fetch` should never give out mutable references.
+pub unsafe trait ReadOnlyFetch: Fetch {}
 
-use crate::{Component, EntityId, World};
+unsafe impl Fetch for EntityId {
+    type Item<'w> = EntityId;
+    type State = *const EntityId;
+
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
 
-pub trait ComponentRef<'t>: Sized + 't {
-    type Value: Component;
+    fn access(_access: &mut Access) {}
 
-    fn from_mut(value: &'t mut Self::Value) -> Self;
+    fn prepare(archetype: &Archetype, _ticks: ChangeTicks) -> Self::State {
+        archetype.entities.as_ptr()
+    }
+
+    unsafe fn fetch<'w>(state: Self::State, entity_index: usize) -> Self::Item<'w> {
+        unsafe { *state.add(entity_index) }
+    }
 }
 
-impl<'t, T: Component> ComponentRef<'t> for &'t T {
-    type Value = T;
+unsafe impl ReadOnlyFetch for EntityId {}
 
-    fn from_mut(value: &'t mut Self::Value) -> Self {
-        value
+unsafe impl<T: Component> Fetch for &T {
+    type Item<'w> = &'w T;
+    type State = *mut T;
+
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.contains::<T>()
+    }
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+
+    fn prepare(archetype: &Archetype, _ticks: ChangeTicks) -> Self::State {
+        archetype.column_ptr::<T>().unwrap()
+    }
+
+    unsafe fn fetch<'w>(state: Self::State, entity_index: usize) -> Self::Item<'w> {
+        unsafe { &*state.add(entity_index) }
     }
 }
 
-impl<'t, T: Component> ComponentRef<'t> for &'t mut T {
-    type Value = T;
+unsafe impl<T: Component> ReadOnlyFetch for &T {}
+
+unsafe impl<T: Component> Fetch for &mut T {
+    type Item<'w> = Mut<'w, T>;
+    type State = (*mut T, *const UnsafeCell<ComponentTicks>, Tick);
+
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.contains::<T>()
+    }
+
+    fn access(access: &mut Access) {
+        access.add_write::<T>();
+    }
+
+    fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State {
+        (
+            archetype.column_ptr::<T>().unwrap(),
+            archetype.ticks_ptr::<T>().unwrap(),
+            ticks.this_run,
+        )
+    }
+
+    unsafe fn fetch<'w>(state: Self::State, entity_index: usize) -> Self::Item<'w> {
+        let (components, ticks, this_run) = state;
 
-    fn from_mut(value: &'t mut Self::Value) -> Self {
-        value
+        unsafe {
+            Mut {
+                value: &mut *components.add(entity_index),
+                ticks: &mut *UnsafeCell::raw_get(ticks.add(entity_index)),
+                this_run,
+            }
+        }
     }
 }
 
-pub trait Query<'w>: Sized + 'w {
-    type Output: 'w;
+/// Fetches `Some` for archetypes matching `Q` and `None` for the rest of them, so that
+/// `Option<&T>` and `Option<&mut T>` query optional components.
+unsafe impl<Q: Fetch> Fetch for Option<Q> {
+    type Item<'w> = Option<Q::Item<'w>>;
+    type State = Option<Q::State>;
 
-    fn query(world: &'w World) -> impl Iterator<Item = Self::Output> + 'w;
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    fn access(access: &mut Access) {
+        Q::access(access);
+    }
+
+    fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State {
+        Q::matches(archetype).then(|| Q::prepare(archetype, ticks))
+    }
+
+    unsafe fn fetch<'w>(state: Self::State, entity_index: usize) -> Self::Item<'w> {
+        state.map(|state| unsafe { Q::fetch(state, entity_index) })
+    }
 }
 
-pub trait QueryMut<'w>: Sized + 'w {
-    type Output: 'w;
+unsafe impl<Q: ReadOnlyFetch> ReadOnlyFetch for Option<Q> {}
+
+/// Fetches whether an entity has component `T` without accessing it.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, Has};
+///
+/// struct Name(&'static str);
+/// impl Component for Name {}
+///
+/// struct Enemy;
+/// impl Component for Enemy {}
+///
+/// let mut world = World::new();
+///
+/// world.spawn(Name("Player"));
+/// world.spawn((Name("Goblin"), Enemy));
+///
+/// let enemies: Vec<_> = world
+///     .query::<(&Name, Has<Enemy>)>()
+///     .map(|(name, is_enemy)| (name.0, is_enemy))
+///     .collect();
+///
+/// assert_eq!(enemies, [("Player", false), ("Goblin", true)]);
+/// ```
+pub struct Has<T>(PhantomData<T>);
+
+unsafe impl<T: Component> Fetch for Has<T> {
+    type Item<'w> = bool;
+    type State = bool;
+
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    fn access(_access: &mut Access) {}
+
+    fn prepare(archetype: &Archetype, _ticks: ChangeTicks) -> Self::State {
+        archetype.contains::<T>()
+    }
 
-    fn query_mut(world: &'w mut World) -> impl Iterator<Item = Self::Output> + 'w;
+    unsafe fn fetch<'w>(state: Self::State, _entity_index: usize) -> Self::Item<'w> {
+        state
+    }
 }
 
-impl<'w, T: Component> Query<'w> for &'w T {
-    type Output = Self;
+unsafe impl<T: Component> ReadOnlyFetch for Has<T> {}
+
+/// Fetches a tuple of optional items from archetypes matching at least one element of the tuple
+/// `T`.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, AnyOf};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Enemy(u32);
+/// impl Component for Enemy {}
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Boss(u32);
+/// impl Component for Boss {}
+///
+/// struct Tree;
+/// impl Component for Tree {}
+///
+/// let mut world = World::new();
+///
+/// world.spawn(Enemy(1));
+/// world.spawn((Enemy(2), Boss(2)));
+/// world.spawn(Tree);
+///
+/// let threats: Vec<_> = world.query::<AnyOf<(&Enemy, &Boss)>>().collect();
+///
+/// assert_eq!(
+///     threats,
+///     [(Some(&Enemy(1)), None), (Some(&Enemy(2)), Some(&Boss(2)))],
+/// );
+/// ```
+pub struct AnyOf<T>(PhantomData<T>);
+
+macro_rules! impl_fetch {
+    ( ( $( $t:ident : $T:ident ),+ ) ) => {
+        unsafe impl< $( $T: Fetch, )+ > Fetch for ( $( $T, )+ ) {
+            type Item<'w> = ( $( $T::Item<'w>, )+ );
+            type State = ( $( $T::State, )+ );
+
+            fn matches(archetype: &Archetype) -> bool {
+                $( $T::matches(archetype) )&&+
+            }
+
+            fn access(access: &mut Access) {
+                $( $T::access(access); )+
+            }
+
+            fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State {
+                ( $( $T::prepare(archetype, ticks), )+ )
+            }
 
-    fn query(world: &'w World) -> impl Iterator<Item = Self::Output> + 'w {
-        world
-            .archetypes
-            .iter()
-            .filter(move |arch| !arch.entities.is_empty() && arch.contains::<T>())
-            .flat_map(move |arch| {
-                let components_index = arch.index[&TypeId::of::<T>()];
-                let mut ptr = arch.components[components_index].cast::<T>();
+            unsafe fn fetch<'w>(state: Self::State, entity_index: usize) -> Self::Item<'w> {
+                let ( $( $t, )+ ) = state;
 
-                if ptr.is_null() {
-                    ptr = std::ptr::NonNull::<T>::dangling().as_ptr();
-                }
+                ( $( unsafe { $T::fetch($t, entity_index) }, )+ )
+            }
+        }
+
+        unsafe impl< $( $T: ReadOnlyFetch, )+ > ReadOnlyFetch for ( $( $T, )+ ) {}
+
+        unsafe impl< $( $T: Fetch, )+ > Fetch for AnyOf<( $( $T, )+ )> {
+            type Item<'w> = ( $( Option<$T::Item<'w>>, )+ );
+            type State = ( $( Option<$T::State>, )+ );
 
-                let components = unsafe { slice::from_raw_parts(ptr, arch.entities.len()) };
+            fn matches(archetype: &Archetype) -> bool {
+                $( $T::matches(archetype) )||+
+            }
+
+            fn access(access: &mut Access) {
+                $( $T::access(access); )+
+            }
 
-                components.iter()
-            })
+            fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State {
+                ( $( $T::matches(archetype).then(|| $T::prepare(archetype, ticks)), )+ )
+            }
+
+            unsafe fn fetch<'w>(state: Self::State, entity_index: usize) -> Self::Item<'w> {
+                let ( $( $t, )+ ) = state;
+
+                ( $( $t.map(|state| unsafe { $T::fetch(state, entity_index) }), )+ )
+            }
+        }
+
+        unsafe impl< $( $T: ReadOnlyFetch, )+ > ReadOnlyFetch for AnyOf<( $( $T, )+ )> {}
+    };
+}
+
+impl_fetch! { (a: A) }
+impl_fetch! { (a: A, b: B) }
+impl_fetch! { (a: A, b: B, c: C) }
+impl_fetch! { (a: A, b: B, c: C, d: D) }
+impl_fetch! { (a: A, b: B, c: C, d: D, e: E) }
+impl_fetch! { (a: A, b: B, c: C, d: D, e: E, f: F) }
+impl_fetch! { (a: A, b: B, c: C, d: D, e: E, f: F, g: G) }
+impl_fetch! { (a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H) }
+
+/// Panics if `Q` accesses some component mutably more than once or both mutably and immutably.
+/// The check only runs once per type and thread.
+pub(crate) fn assert_unique_access<Q: Fetch + 'static>() {
+    thread_local! {
+        /// Types already checked on this thread.
+        static CHECKED: RefCell<HashSet<TypeId>> = RefCell::new(HashSet::new());
+    }
+
+    if CHECKED.with_borrow(|checked| checked.contains(&TypeId::of::<Q>())) {
+        return;
+    }
+
+    let mut access = Access::default();
+    Q::access(&mut access);
+
+    if let Some(name) = access.conflict() {
+        panic!(
+            "`{}` accesses component `{name}` mutably while also accessing it elsewhere",
+            type_name::<Q>(),
+        );
     }
+
+    CHECKED.with_borrow_mut(|checked| checked.insert(TypeId::of::<Q>()));
 }
 
-macro_rules! impl_query {
-    ( @map ) => {
-        |x| (x,)
-    };
-    ( @map $A:ident ) => {
-        |(x, y)| (x, y)
-    };
-    ( @map $A:ident $B:ident ) => {
-        |((x, y), z)| (x, y, z)
-    };
-    ( @map $A:ident $B:ident $C:ident ) => {
-        |(((x, y), z), w)| (x, y, z, w)
-    };
-    ( @map $A:ident $B:ident $C:ident $D:ident ) => {
-        |((((a, b), c), d), e)| (a, b, c, d, e)
-    };
-    ( @map $A:ident $B:ident $C:ident $D:ident $E:ident ) => {
-        |(((((a, b), c), d), e), f)| (a, b, c, d, e, f)
-    };
-    ( @map $A:ident $B:ident $C:ident $D:ident $E:ident $F:ident ) => {
-        |((((((a, b), c), d), e), f), g)| (a, b, c, d, e, f, g)
-    };
-    ( @map $A:ident $B:ident $C:ident $D:ident $E:ident $F:ident $G:ident ) => {
-        |(((((((a, b), c), d), e), f), g), h)| (a, b, c, d, e, f, g, h)
+/// A condition restricting the rows of a query without fetching any data. Implemented for
+/// [`With`], [`Without`], [`Added`], [`Changed`], [`Or`] and tuples of them, which match if every
+/// element matches.
+///
+/// # Safety
+///
+/// - `filter` should only access the row `entity_index` of the prepared archetype.
+/// - `access` should record every component `filter` reads.
+/// - `filter` should always return `true` if `IS_ARCHETYPAL` is `true`.
+pub unsafe trait Filter {
+    /// Per-archetype state used to filter single rows.
+    type State: Copy;
+
+    /// Whether the filter is decided by [`Filter::matches`] alone.
+    const IS_ARCHETYPAL: bool;
+
+    /// Checks if some rows of the archetype can pass the filter.
+    fn matches(archetype: &Archetype) -> bool;
+
+    /// Records the components this filter reads.
+    fn access(access: &mut Access);
+
+    /// Prepares the state for the archetype. Changes are reported if they happened after
+    /// `ticks.last_run`.
+    fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State;
+
+    /// Checks if the row `entity_index` of the prepared archetype passes the filter.
+    ///
+    /// # Safety
+    ///
+    /// `entity_index` should be less than the length of the prepared archetype.
+    unsafe fn filter(state: Self::State, entity_index: usize) -> bool;
+}
+
+unsafe impl Filter for () {
+    type State = ();
+
+    const IS_ARCHETYPAL: bool = true;
+
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+
+    fn access(_access: &mut Access) {}
+
+    fn prepare(_archetype: &Archetype, _ticks: ChangeTicks) -> Self::State {}
+
+    unsafe fn filter(_state: Self::State, _entity_index: usize) -> bool {
+        true
+    }
+}
+
+/// Filters entities that have component `T`.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, With, Without};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Position(f32);
+/// impl Component for Position {}
+///
+/// struct Player;
+/// impl Component for Player {}
+///
+/// struct Dead;
+/// impl Component for Dead {}
+///
+/// let mut world = World::new();
+///
+/// world.spawn((Position(1.0), Player));
+/// world.spawn((Position(2.0), Player, Dead));
+/// world.spawn(Position(3.0));
+///
+/// let alive_players: Vec<_> = world
+///     .query_filtered::<(&Position,), (With<Player>, Without<Dead>)>()
+///     .collect();
+///
+/// assert_eq!(alive_players, [(&Position(1.0),)]);
+/// ```
+pub struct With<T>(PhantomData<T>);
+
+unsafe impl<T: Component> Filter for With<T> {
+    type State = ();
+
+    const IS_ARCHETYPAL: bool = true;
+
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.contains::<T>()
+    }
+
+    fn access(_access: &mut Access) {}
+
+    fn prepare(_archetype: &Archetype, _ticks: ChangeTicks) -> Self::State {}
+
+    unsafe fn filter(_state: Self::State, _entity_index: usize) -> bool {
+        true
+    }
+}
+
+/// Filters entities that do not have component `T`. See [`With`] for an example.
+pub struct Without<T>(PhantomData<T>);
+
+unsafe impl<T: Component> Filter for Without<T> {
+    type State = ();
+
+    const IS_ARCHETYPAL: bool = true;
+
+    fn matches(archetype: &Archetype) -> bool {
+        !archetype.contains::<T>()
+    }
+
+    fn access(_access: &mut Access) {}
+
+    fn prepare(_archetype: &Archetype, _ticks: ChangeTicks) -> Self::State {}
+
+    unsafe fn filter(_state: Self::State, _entity_index: usize) -> bool {
+        true
+    }
+}
+
+/// Filters entities whose component `T` was added after the last run of the query, see
+/// [`World::clear_trackers`](crate::World::clear_trackers). Inserting a component the entity
+/// already has counts as adding it.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, Added};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Name(&'static str);
+/// impl Component for Name {}
+///
+/// let mut world = World::new();
+///
+/// world.spawn(Name("Old"));
+/// world.clear_trackers();
+/// world.spawn(Name("New"));
+///
+/// let added: Vec<_> = world.query_filtered::<&Name, Added<Name>>().collect();
+///
+/// assert_eq!(added, [&Name("New")]);
+/// ```
+pub struct Added<T>(PhantomData<T>);
+
+unsafe impl<T: Component> Filter for Added<T> {
+    type State = (*const UnsafeCell<ComponentTicks>, Tick);
+
+    const IS_ARCHETYPAL: bool = false;
+
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.contains::<T>()
+    }
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+
+    fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State {
+        (archetype.ticks_ptr::<T>().unwrap(), ticks.last_run)
+    }
+
+    unsafe fn filter(state: Self::State, entity_index: usize) -> bool {
+        let (ticks, last_run) = state;
+
+        unsafe { (*UnsafeCell::raw_get(ticks.add(entity_index))).added > last_run }
+    }
+}
+
+/// Filters entities whose component `T` was added or accessed mutably after the last run of the
+/// query, see [`Mut`] for an example.
+pub struct Changed<T>(PhantomData<T>);
+
+unsafe impl<T: Component> Filter for Changed<T> {
+    type State = (*const UnsafeCell<ComponentTicks>, Tick);
+
+    const IS_ARCHETYPAL: bool = false;
+
+    fn matches(archetype: &Archetype) -> bool {
+        archetype.contains::<T>()
+    }
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+
+    fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State {
+        (archetype.ticks_ptr::<T>().unwrap(), ticks.last_run)
+    }
+
+    unsafe fn filter(state: Self::State, entity_index: usize) -> bool {
+        let (ticks, last_run) = state;
+
+        unsafe { (*UnsafeCell::raw_get(ticks.add(entity_index))).changed > last_run }
+    }
+}
+
+/// Filters entities passing at least one filter of the tuple `T`.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, Or, With, Without};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Name(&'static str);
+/// impl Component for Name {}
+///
+/// struct Enemy;
+/// impl Component for Enemy {}
+///
+/// struct Boss;
+/// impl Component for Boss {}
+///
+/// struct Dead;
+/// impl Component for Dead {}
+///
+/// let mut world = World::new();
+///
+/// world.spawn((Name("Goblin"), Enemy));
+/// world.spawn((Name("Dragon"), Boss));
+/// world.spawn((Name("Skeleton"), Enemy, Dead));
+/// world.spawn(Name("Villager"));
+///
+/// let threats: Vec<_> = world
+///     .query_filtered::<&Name, (Or<(With<Enemy>, With<Boss>)>, Without<Dead>)>()
+///     .collect();
+///
+/// assert_eq!(threats, [&Name("Goblin"), &Name("Dragon")]);
+/// ```
+pub struct Or<T>(PhantomData<T>);
+
+macro_rules! impl_filter {
+    ( $( $f:ident : $F:ident ),+ ) => {
+        unsafe impl< $( $F: Filter, )+ > Filter for ( $( $F, )+ ) {
+            type State = ( $( $F::State, )+ );
+
+            const IS_ARCHETYPAL: bool = $( $F::IS_ARCHETYPAL )&&+;
+
+            fn matches(archetype: &Archetype) -> bool {
+                $( $F::matches(archetype) )&&+
+            }
+
+            fn access(access: &mut Access) {
+                $( $F::access(access); )+
+            }
+
+            fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State {
+                ( $( $F::prepare(archetype, ticks), )+ )
+            }
+
+            unsafe fn filter(state: Self::State, entity_index: usize) -> bool {
+                let ( $( $f, )+ ) = state;
+
+                true $( && unsafe { $F::filter($f, entity_index) } )+
+            }
+        }
+
+        unsafe impl< $( $F: Filter, )+ > Filter for Or<( $( $F, )+ )> {
+            type State = ( $( Option<$F::State>, )+ );
+
+            const IS_ARCHETYPAL: bool = $( $F::IS_ARCHETYPAL )&&+;
+
+            fn matches(archetype: &Archetype) -> bool {
+                $( $F::matches(archetype) )||+
+            }
+
+            fn access(access: &mut Access) {
+                $( $F::access(access); )+
+            }
+
+            fn prepare(archetype: &Archetype, ticks: ChangeTicks) -> Self::State {
+                ( $( $F::matches(archetype).then(|| $F::prepare(archetype, ticks)), )+ )
+            }
+
+            unsafe fn filter(state: Self::State, entity_index: usize) -> bool {
+                let ( $( $f, )+ ) = state;
+
+                $( $f.is_some_and(|state| unsafe { $F::filter(state, entity_index) }) )||+
+            }
+        }
     };
-    ( $T:ident $( $Tail:ident )* ) => {
-        impl<'w, $T: Component, $( $Tail: Component, )* > Query<'w> for (EntityId, &'w $T, $( &'w $Tail, )* ) {
-            type Output = Self;
-
-            fn query(world: &'w World) -> impl Iterator<Item = Self::Output> + 'w {
-                world
-                    .archetypes
-                    .iter()
-                    .filter(move |arch| {
-                        !arch.entities.is_empty()
-                            && arch.contains::< $T >()
-                            $(
-                                && arch.contains::< $Tail >()
-                            )*
-                    })
-                    .flat_map(move |arch| {
-                        arch.entities
-                            .iter()
-                            .copied()
-                            .zip({
-                                let components_index = arch.index[&TypeId::of::< $T >()];
-                                let mut ptr = arch.components[components_index].cast::< $T >();
-
-                                if ptr.is_null() {
-                                    ptr = std::ptr::NonNull::< $T >::dangling().as_ptr();
-                                }
-
-                                unsafe { slice::from_raw_parts(ptr, arch.entities.len()) }
-                            })
-                            $(
-                                .zip({
-                                    let components_index = arch.index[&TypeId::of::< $Tail >()];
-                                    let mut ptr = arch.components[components_index].cast::< $Tail >();
-
-                                    if ptr.is_null() {
-                                        ptr = std::ptr::NonNull::< $Tail >::dangling().as_ptr();
-                                    }
-
-                                    unsafe { slice::from_raw_parts(ptr, arch.entities.len()) }
-                                })
-                            )*
-                            .map(impl_query!(@map $T $( $Tail )* ))
-                    })
+}
+
+impl_filter! { a: A }
+impl_filter! { a: A, b: B }
+impl_filter! { a: A, b: B, c: C }
+impl_filter! { a: A, b: B, c: C, d: D }
+impl_filter! { a: A, b: B, c: C, d: D, e: E }
+impl_filter! { a: A, b: B, c: C, d: D, e: E, f: F }
+impl_filter! { a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_filter! { a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
+
+/// An iterator over the query results, created by [`World::query`](crate::World::query) and
+/// [`World::query_mut`](crate::World::query_mut).
+pub struct QueryIter<'w, Q: Fetch, F: Filter = ()> {
+    archetypes: slice::Iter<'w, Archetype>,
+    ticks: ChangeTicks,
+    state: Option<(Q::State, F::State)>,
+    entity_index: usize,
+    len: usize,
+}
+
+impl<'w, Q: Fetch, F: Filter> QueryIter<'w, Q, F> {
+    /// # Safety
+    ///
+    /// The archetypes should be borrowed mutably if `Q` is not [`ReadOnlyFetch`].
+    pub(crate) unsafe fn new(archetypes: &'w [Archetype], ticks: ChangeTicks) -> Self {
+        Self {
+            archetypes: archetypes.iter(),
+            ticks,
+            state: None,
+            entity_index: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<'w, Q: Fetch, F: Filter> Iterator for QueryIter<'w, Q, F> {
+    type Item = Q::Item<'w>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((state, filter)) = self.state
+                && self.entity_index < self.len
+            {
+                let entity_index = self.entity_index;
+                self.entity_index += 1;
+
+                if !unsafe { F::filter(filter, entity_index) } {
+                    continue;
+                }
+
+                return Some(unsafe { Q::fetch(state, entity_index) });
+            }
+
+            let archetype = self.archetypes.next()?;
+
+            if archetype.is_empty() || !Q::matches(archetype) || !F::matches(archetype) {
+                continue;
             }
+
+            self.state = Some((
+                Q::prepare(archetype, self.ticks),
+                F::prepare(archetype, self.ticks),
+            ));
+            self.entity_index = 0;
+            self.len = archetype.len();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.entity_index;
+        let lower = if F::IS_ARCHETYPAL { remaining } else { 0 };
+
+        match self.archetypes.len() {
+            0 => (lower, Some(remaining)),
+            _ => (lower, None),
         }
+    }
+}
 
-        impl<'w, $T: Component, $( $Tail: Component, )* > Query<'w> for (&'w $T, $( &'w $Tail, )* ) {
-            type Output = Self;
-
-            fn query(world: &'w World) -> impl Iterator<Item = Self::Output> + 'w {
-                world
-                    .archetypes
-                    .iter()
-                    .filter(move |arch| {
-                        !arch.entities.is_empty()
-                            && arch.contains::< $T >()
-                            $(
-                                && arch.contains::< $Tail >()
-                            )*
-                    })
-                    .flat_map(move |arch| {
-                        {
-                            let components_index = arch.index[&TypeId::of::< $T >()];
-                            let mut ptr = arch.components[components_index].cast::< $T >();
-
-                            if ptr.is_null() {
-                                ptr = std::ptr::NonNull::< $T >::dangling().as_ptr();
-                            }
-
-                            unsafe { slice::from_raw_parts(ptr, arch.entities.len()) }
-                        }.into_iter()
-                            $(
-                                .zip({
-                                    let components_index = arch.index[&TypeId::of::< $Tail >()];
-                                    let mut ptr = arch.components[components_index].cast::< $Tail >();
-
-                                    if ptr.is_null() {
-                                        ptr = std::ptr::NonNull::< $Tail >::dangling().as_ptr();
-                                    }
-
-                                    unsafe { slice::from_raw_parts(ptr, arch.entities.len()) }
-                                })
-                            )*
-                            .map(impl_query!(@map $( $Tail )* ))
-                    })
+impl<Q: Fetch, F: Filter> std::iter::FusedIterator for QueryIter<'_, Q, F> {}
+
+/// The smallest number of rows [`QueryIter::par_for_each`] hands to a thread at once.
+const MIN_BATCH_SIZE: usize = 1024;
+
+impl<'w, Q: Fetch, F: Filter> QueryIter<'w, Q, F> {
+    /// Calls `f` on every remaining item using several threads. Matching archetypes are split
+    /// into batches of rows, so that a single large archetype is processed by several threads
+    /// as well. Uses scoped threads, or the global thread pool of `rayon` if the `rayon` feature
+    /// is enabled.
+    ///
+    /// Only available if the items can be sent to other threads, that is, the components read
+    /// are [`Sync`] and the ones modified are [`Send`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// struct Position(i64);
+    /// impl Component for Position {}
+    ///
+    /// struct Velocity(i64);
+    /// impl Component for Velocity {}
+    ///
+    /// let mut world = World::new();
+    /// world.spawn_batch((0..10_000).map(|i| (Position(0), Velocity(i)))).for_each(drop);
+    ///
+    /// world
+    ///     .query_mut::<(&mut Position, &Velocity)>()
+    ///     .par_for_each(|(mut position, velocity)| position.0 += velocity.0);
+    ///
+    /// let sum: i64 = world.query::<&Position>().map(|position| position.0).sum();
+    /// assert_eq!(sum, (0..10_000).sum());
+    /// ```
+    pub fn par_for_each(self, f: impl Fn(Q::Item<'w>) + Sync)
+    where
+        Q::Item<'w>: Send,
+    {
+        #[cfg(feature = "rayon")]
+        let n_threads = rayon::current_num_threads();
+
+        #[cfg(not(feature = "rayon"))]
+        let n_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+        let batches = self.into_batches(n_threads);
+
+        if n_threads == 1 || batches.len() <= 1 {
+            for batch in &batches {
+                // Safety: the batches do not overlap
+                unsafe { batch.run(&f) };
             }
+
+            return;
+        }
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            // Safety: the batches do not overlap
+            batches
+                .par_iter()
+                .for_each(|batch| unsafe { batch.run(&f) });
         }
 
-        impl<'w, $T: ComponentRef<'w>, $( $Tail: ComponentRef<'w>, )*> QueryMut<'w> for (EntityId, $T, $( $Tail, )*) {
-            type Output = Self;
-
-            fn query_mut(world: &'w mut World) -> impl Iterator<Item = Self::Output> + 'w {
-                world
-                    .archetypes
-                    .iter_mut()
-                    .filter(move |arch| {
-                        !arch.entities.is_empty()
-                            && arch.contains::< $T ::Value>()
-                            $(
-                                && arch.contains::< $Tail ::Value>()
-                            )*
-                    })
-                    .flat_map(move |arch| {
-                        arch.entities
-                            .iter()
-                            .copied()
-                            .zip(
-                                {
-                                    let components_index = arch.index[&TypeId::of::< $T ::Value>()];
-                                    let mut ptr = arch.components[components_index].cast::< $T ::Value>();
-
-                                    if ptr.is_null() {
-                                        ptr = std::ptr::NonNull::< $T ::Value>::dangling().as_ptr();
-                                    }
-
-                                    unsafe { slice::from_raw_parts_mut(ptr, arch.entities.len()) }
-                                }
-                                .iter_mut()
-                                .map( $T ::from_mut),
-                            )
-                            $(
-                                .zip(
-                                    {
-                                        let components_index = arch.index[&TypeId::of::< $Tail ::Value>()];
-                                        let mut ptr = arch.components[components_index].cast::< $Tail ::Value>();
-
-                                        if ptr.is_null() {
-                                            ptr = std::ptr::NonNull::< $Tail ::Value>::dangling().as_ptr();
-                                        }
-
-                                        unsafe { slice::from_raw_parts_mut(ptr, arch.entities.len()) }
-                                    }
-                                    .iter_mut()
-                                    .map( $Tail ::from_mut),
-                                )
-                            )*
-                            .map(impl_query!(@map $T $( $Tail )* ))
-                    })
+        #[cfg(not(feature = "rayon"))]
+        {
+            use std::{
+                sync::atomic::{AtomicUsize, Ordering},
+                thread,
+            };
+
+            let next = AtomicUsize::new(0);
+
+            let work = || {
+                while let Some(batch) = batches.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    // Safety: every batch is taken once and the batches do not overlap
+                    unsafe { batch.run(&f) };
+                }
+            };
+
+            thread::scope(|scope| {
+                for _ in 1..n_threads.min(batches.len()) {
+                    scope.spawn(work);
+                }
+
+                work();
+            });
+        }
+    }
+
+    /// Splits the remaining rows into batches of roughly equal size.
+    fn into_batches(self, n_threads: usize) -> Vec<Batch<'w, Q, F>> {
+        let mut ranges = vec![];
+
+        if let Some((state, filter)) = self.state
+            && self.entity_index < self.len
+        {
+            ranges.push((state, filter, self.entity_index..self.len));
+        }
+
+        for archetype in self.archetypes {
+            if archetype.is_empty() || !Q::matches(archetype) || !F::matches(archetype) {
+                continue;
             }
+
+            ranges.push((
+                Q::prepare(archetype, self.ticks),
+                F::prepare(archetype, self.ticks),
+                0..archetype.len(),
+            ));
         }
 
-        impl<'w, $T: ComponentRef<'w>, $( $Tail: ComponentRef<'w>, )*> QueryMut<'w> for ($T, $( $Tail, )*) {
-            type Output = Self;
-
-            fn query_mut(world: &'w mut World) -> impl Iterator<Item = Self::Output> + 'w {
-                world
-                    .archetypes
-                    .iter_mut()
-                    .filter(move |arch| {
-                        !arch.entities.is_empty()
-                            && arch.contains::< $T ::Value>()
-                            $(
-                                && arch.contains::< $Tail ::Value>()
-                            )*
-                    })
-                    .flat_map(move |arch| {
-                            {
-                                let components_index = arch.index[&TypeId::of::< $T ::Value>()];
-                                let mut ptr = arch.components[components_index].cast::< $T ::Value>();
-
-                                if ptr.is_null() {
-                                    ptr = std::ptr::NonNull::< $T ::Value>::dangling().as_ptr();
-                                }
-
-                                unsafe { slice::from_raw_parts_mut(ptr, arch.entities.len()) }
-                            }
-                            .iter_mut()
-                            .map( $T ::from_mut)
-                            $(
-                                .zip(
-                                    {
-                                        let components_index = arch.index[&TypeId::of::< $Tail ::Value>()];
-                                        let mut ptr = arch.components[components_index].cast::< $Tail ::Value>();
-
-                                        if ptr.is_null() {
-                                            ptr = std::ptr::NonNull::< $Tail ::Value>::dangling().as_ptr();
-                                        }
-
-                                        unsafe { slice::from_raw_parts_mut(ptr, arch.entities.len()) }
-                                    }
-                                    .iter_mut()
-                                    .map( $Tail ::from_mut),
-                                )
-                            )*
-                            .map(impl_query!(@map $( $Tail )* ))
-                    })
+        let n_rows: usize = ranges.iter().map(|(_, _, rows)| rows.len()).sum();
+        let batch_size = n_rows.div_ceil(n_threads).max(MIN_BATCH_SIZE);
+
+        let mut batches = vec![];
+
+        for (state, filter, rows) in ranges {
+            for start in rows.clone().step_by(batch_size) {
+                batches.push(Batch {
+                    state,
+                    filter,
+                    rows: start..rows.end.min(start + batch_size),
+                    _items: PhantomData,
+                });
             }
         }
-    };
+
+        batches
+    }
 }
 
-impl_query! { A }
-impl_query! { A B }
-impl_query! { A B C }
-impl_query! { A B C D }
-impl_query! { A B C D E }
-impl_query! { A B C D E F }
-impl_query! { A B C D E F G }
+/// Rows of a single archetype processed by [`QueryIter::par_for_each`] on one thread.
+struct Batch<'w, Q: Fetch, F: Filter> {
+    state: Q::State,
+    filter: F::State,
+    rows: Range<usize>,
+    _items: PhantomData<fn() -> Q::Item<'w>>,
+}
+
+// Safety: the state only points into the columns of the archetype and the batch yields nothing but
+// items, so handing it to other threads is fine as long as the items can be sent
+unsafe impl<'w, Q: Fetch, F: Filter> Send for Batch<'w, Q, F> where Q::Item<'w>: Send {}
+unsafe impl<'w, Q: Fetch, F: Filter> Sync for Batch<'w, Q, F> where Q::Item<'w>: Send {}
+
+impl<'w, Q: Fetch, F: Filter> Batch<'w, Q, F> {
+    /// # Safety
+    ///
+    /// The rows should not be fetched by anyone else at the same time.
+    unsafe fn run(&self, f: &impl Fn(Q::Item<'w>)) {
+        for entity_index in self.rows.clone() {
+            if unsafe { F::filter(self.filter, entity_index) } {
+                f(unsafe { Q::fetch(self.state, entity_index) });
+            }
+        }
+    }
+}
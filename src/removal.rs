@@ -0,0 +1,73 @@
+use crate::{change::Tick, EntityId};
+use std::{
+    any::TypeId,
+    collections::{HashMap, VecDeque},
+};
+
+/// Decides how long the [`World`](crate::World) remembers removed components, see
+/// [`World::removed`](crate::World::removed).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RemovalRetention {
+    /// Removals are kept until [`World::clear_removed`](crate::World::clear_removed) is called.
+    Manual,
+    /// Removals are kept for given number of frames, that is, calls to
+    /// [`World::clear_trackers`](crate::World::clear_trackers) counting the current one. With
+    /// `Frames(2)` removals made during the previous frame are still visible.
+    Frames(u32),
+}
+
+impl Default for RemovalRetention {
+    fn default() -> Self {
+        Self::Frames(2)
+    }
+}
+
+/// The log of removed components keyed by their [`TypeId`].
+#[derive(Default)]
+pub(crate) struct Removals {
+    logs: HashMap<TypeId, Vec<(EntityId, Tick)>>,
+    frame_starts: VecDeque<Tick>,
+    pub retention: RemovalRetention,
+}
+
+impl Removals {
+    pub fn push(&mut self, id: TypeId, entity: EntityId, tick: Tick) {
+        self.logs.entry(id).or_default().push((entity, tick));
+    }
+
+    /// Entities that lost component `id` after `tick` in the order of removal.
+    pub fn since(&self, id: TypeId, tick: Tick) -> impl Iterator<Item = EntityId> + '_ {
+        let log = self.logs.get(&id).map_or(&[][..], Vec::as_slice);
+        let start = log.partition_point(|&(_, removed)| removed <= tick);
+
+        log[start..].iter().map(|&(entity, _)| entity)
+    }
+
+    /// Starts a new frame at `tick` dropping removals that are too old to be retained.
+    pub fn start_frame(&mut self, tick: Tick) {
+        let RemovalRetention::Frames(n_frames) = self.retention else {
+            return;
+        };
+
+        self.frame_starts.push_back(tick);
+
+        while self.frame_starts.len() > n_frames as usize {
+            self.frame_starts.pop_front();
+        }
+
+        if self.frame_starts.len() < n_frames as usize {
+            return;
+        }
+
+        let oldest = self.frame_starts.front().copied().unwrap_or(tick);
+
+        for log in self.logs.values_mut() {
+            let expired = log.partition_point(|&(_, removed)| removed < oldest);
+            log.drain(..expired);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.logs.clear();
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    any::{Any, TypeId},
+    cell::UnsafeCell,
+    collections::HashMap,
+};
+
+/// Signifies that given type can be stored in the [`World`](crate::World) as a global unique
+/// value, e.g. frame time, random number generator or configuration. Resources should be [`Send`]
+/// and [`Sync`] for the same reasons [`Component`](crate::Component)s are.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::Resource;
+///
+/// struct DeltaTime(f32);
+/// impl Resource for DeltaTime {}
+/// ```
+pub trait Resource: Send + Sync + 'static {}
+
+/// A single resource. The value is kept in an [`UnsafeCell`] so that disjoint resources can be
+/// borrowed mutably through a shared world.
+struct ResourceCell<R>(UnsafeCell<R>);
+
+// Safety: the cell is only accessed according to Rust's borrowing rules, either through the
+// `Resources` borrow or through access checked by the caller
+unsafe impl<R: Resource> Sync for ResourceCell<R> {}
+
+/// Type-erased storage of resources keyed by their [`TypeId`].
+#[derive(Default)]
+pub(crate) struct Resources {
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Resources {
+    /// Inserts the resource returning the previous value of the same type.
+    pub fn insert<R: Resource>(&mut self, value: R) -> Option<R> {
+        match self.get_mut::<R>() {
+            Some(old) => Some(std::mem::replace(old, value)),
+            None => {
+                let cell = Box::new(ResourceCell(UnsafeCell::new(value)));
+                self.values.insert(TypeId::of::<R>(), cell);
+                None
+            }
+        }
+    }
+
+    pub fn remove<R: Resource>(&mut self) -> Option<R> {
+        let cell = self.values.remove(&TypeId::of::<R>())?;
+        let cell = cell.downcast::<ResourceCell<R>>().ok()?;
+
+        Some(cell.0.into_inner())
+    }
+
+    pub fn contains<R: Resource>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<R>())
+    }
+
+    pub fn get<R: Resource>(&self) -> Option<&R> {
+        // Safety: there are no mutable borrows while `self` is borrowed immutably
+        self.get_ptr::<R>().map(|ptr| unsafe { &*ptr })
+    }
+
+    pub fn get_mut<R: Resource>(&mut self) -> Option<&mut R> {
+        // Safety: `self` is borrowed mutably
+        self.get_ptr::<R>().map(|ptr| unsafe { &mut *ptr })
+    }
+
+    /// Pointer to the resource. Dereferencing it is only sound if the resource is not borrowed
+    /// in a conflicting way.
+    pub fn get_ptr<R: Resource>(&self) -> Option<*mut R> {
+        let cell = self.values.get(&TypeId::of::<R>())?;
+        let cell = cell.downcast_ref::<ResourceCell<R>>()?;
+
+        Some(cell.0.get())
+    }
+}
@@ -0,0 +1,447 @@
+use crate::{
+    executor::{self, ExecutorKind, WorkerPool},
+    system::{IntoSystem, System, SystemAccess},
+    TecsError, World,
+};
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    thread,
+};
+
+/// Names of labels and sets a system or a set runs before and after.
+#[derive(Clone, Debug, Default)]
+struct Ordering {
+    before: Vec<Cow<'static, str>>,
+    after: Vec<Cow<'static, str>>,
+}
+
+/// A system together with its label, sets and ordering constraints, see [`IntoSystemConfig`].
+pub struct SystemConfig {
+    pub(crate) system: Box<dyn System>,
+    label: Option<Cow<'static, str>>,
+    sets: Vec<Cow<'static, str>>,
+    ordering: Ordering,
+}
+
+/// Conversion into a [`SystemConfig`] added to a [`Schedule`]. Implemented for everything that
+/// converts into a [`System`] and for configs themselves, so that the methods can be chained.
+pub trait IntoSystemConfig<Marker>: Sized {
+    /// Converts into the config.
+    fn into_config(self) -> SystemConfig;
+
+    /// Names the system, so that other systems can be ordered relative to it. Labels should be
+    /// unique within a schedule.
+    fn label(self, label: impl Into<Cow<'static, str>>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.label = Some(label.into());
+        config
+    }
+
+    /// Adds the system to a named set. Ordering constraints relative to the set apply to every
+    /// system in it, see [`Schedule::configure_set`].
+    fn in_set(self, set: impl Into<Cow<'static, str>>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.sets.push(set.into());
+        config
+    }
+
+    /// Runs the system before the systems with given label or in given set.
+    fn before(self, name: impl Into<Cow<'static, str>>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.ordering.before.push(name.into());
+        config
+    }
+
+    /// Runs the system after the systems with given label or in given set.
+    fn after(self, name: impl Into<Cow<'static, str>>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.ordering.after.push(name.into());
+        config
+    }
+}
+
+/// Marks the implementation of [`IntoSystemConfig`] for configs themselves.
+pub struct IsSystemConfig;
+
+impl IntoSystemConfig<IsSystemConfig> for SystemConfig {
+    fn into_config(self) -> SystemConfig {
+        self
+    }
+}
+
+impl<Marker, S: IntoSystem<Marker>> IntoSystemConfig<Marker> for S {
+    fn into_config(self) -> SystemConfig {
+        SystemConfig {
+            system: Box::new(self.into_system()),
+            label: None,
+            sets: vec![],
+            ordering: Ordering::default(),
+        }
+    }
+}
+
+/// Ordering constraints of a named set of systems, see [`Schedule::configure_set`].
+#[derive(Clone, Debug, Default)]
+pub struct SetConfig {
+    ordering: Ordering,
+}
+
+impl SetConfig {
+    /// Runs the systems of the set before the systems with given label or in given set.
+    pub fn before(&mut self, name: impl Into<Cow<'static, str>>) -> &mut Self {
+        self.ordering.before.push(name.into());
+        self
+    }
+
+    /// Runs the systems of the set after the systems with given label or in given set.
+    pub fn after(&mut self, name: impl Into<Cow<'static, str>>) -> &mut Self {
+        self.ordering.after.push(name.into());
+        self
+    }
+}
+
+/// Systems sorted by their ordering constraints.
+pub(crate) struct ScheduleGraph {
+    /// Indices of the systems in the order they run.
+    pub order: Vec<usize>,
+    /// Sorted indices of the systems each system runs after.
+    pub dependencies: Vec<Vec<usize>>,
+    /// Indices of the systems each system runs before.
+    pub dependents: Vec<Vec<usize>>,
+    /// The accesses of the systems.
+    pub accesses: Vec<SystemAccess>,
+}
+
+/// A collection of systems run in the order given by their `before` and `after` constraints.
+/// Systems without constraints between them may run at the same time, unless their accesses
+/// conflict, see [`ExecutorKind`]. Otherwise they start in the order they were added.
+///
+/// Changes deferred by a system, e.g. through [`Commands`](crate::Commands), are applied at sync
+/// points: right before a system ordered after it runs, and at the end of the run.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Resource, ResMut, Schedule, IntoSystemConfig};
+///
+/// #[derive(Default)]
+/// struct Log(Vec<&'static str>);
+/// impl Resource for Log {}
+///
+/// fn input(mut log: ResMut<Log>) {
+///     log.0.push("input");
+/// }
+///
+/// fn movement(mut log: ResMut<Log>) {
+///     log.0.push("movement");
+/// }
+///
+/// fn render(mut log: ResMut<Log>) {
+///     log.0.push("render");
+/// }
+///
+/// let mut world = World::new();
+/// world.insert_resource(Log::default());
+///
+/// let mut schedule = Schedule::new();
+/// schedule
+///     .add_system(render.after("physics"))
+///     .add_system(movement.in_set("physics"))
+///     .add_system(input.label("input"));
+/// schedule.configure_set("physics").after("input");
+///
+/// schedule.run(&mut world);
+/// schedule.run(&mut world);
+///
+/// assert_eq!(
+///     world.resource::<Log>().0,
+///     ["input", "movement", "render", "input", "movement", "render"],
+/// );
+/// ```
+pub struct Schedule {
+    systems: Vec<SystemConfig>,
+    sets: HashMap<Cow<'static, str>, SetConfig>,
+    graph: Option<ScheduleGraph>,
+    executor: ExecutorKind,
+    n_threads: usize,
+    /// Started by the first multi-threaded run.
+    pool: Option<WorkerPool>,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            systems: vec![],
+            sets: HashMap::new(),
+            graph: None,
+            executor: ExecutorKind::default(),
+            n_threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            pool: None,
+        }
+    }
+}
+
+impl Schedule {
+    /// Constructs new empty schedule.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The way the systems are run.
+    pub fn executor(&self) -> ExecutorKind {
+        self.executor
+    }
+
+    /// Changes the way the systems are run, e.g. to run them on a single thread while debugging.
+    pub fn set_executor(&mut self, executor: ExecutorKind) -> &mut Self {
+        self.executor = executor;
+        self
+    }
+
+    /// The maximum number of threads running systems at the same time, including the thread
+    /// running the schedule. Defaults to [`std::thread::available_parallelism`].
+    pub fn thread_count(&self) -> usize {
+        self.n_threads
+    }
+
+    /// Sets the maximum number of threads running systems at the same time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is zero.
+    pub fn set_thread_count(&mut self, count: usize) -> &mut Self {
+        assert_ne!(count, 0, "a schedule needs at least one thread");
+
+        if count != self.n_threads {
+            self.n_threads = count;
+            self.pool = None;
+        }
+
+        self
+    }
+
+    /// The number of systems in the schedule.
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    /// Checks if there are no systems in the schedule.
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Adds a system with its ordering constraints.
+    pub fn add_system<Marker>(&mut self, system: impl IntoSystemConfig<Marker>) -> &mut Self {
+        self.systems.push(system.into_config());
+        self.graph = None;
+        self
+    }
+
+    /// Accesses the ordering constraints of a named set.
+    pub fn configure_set(&mut self, set: impl Into<Cow<'static, str>>) -> &mut SetConfig {
+        self.graph = None;
+        self.sets.entry(set.into()).or_default()
+    }
+
+    /// Initializes the systems and sorts them if they have changed since the previous call.
+    /// Fails if the ordering constraints form a cycle or name a label or set no system has, or if
+    /// some label is used more than once.
+    pub fn initialize(&mut self, world: &mut World) -> Result<(), TecsError> {
+        for config in &mut self.systems {
+            config.system.initialize(world);
+        }
+
+        if self.graph.is_none() {
+            self.graph = Some(self.build_graph()?);
+        }
+
+        Ok(())
+    }
+
+    /// Runs every system once, see [`Schedule::try_run`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the schedule cannot be sorted.
+    pub fn run(&mut self, world: &mut World) {
+        if let Err(error) = self.try_run(world) {
+            panic!("{error}");
+        }
+    }
+
+    /// Runs every system once, applying their deferred changes at sync points. Fails
+    /// if the schedule cannot be sorted, see [`Schedule::initialize`].
+    pub fn try_run(&mut self, world: &mut World) -> Result<(), TecsError> {
+        self.initialize(world)?;
+
+        let Some(graph) = &self.graph else {
+            unreachable!("the schedule is sorted on initialization");
+        };
+
+        match self.executor {
+            ExecutorKind::SingleThreaded => {
+                executor::run_single_threaded(&mut self.systems, graph, world);
+            }
+            ExecutorKind::MultiThreaded => {
+                let pool = self
+                    .pool
+                    .get_or_insert_with(|| WorkerPool::new(self.n_threads - 1));
+
+                executor::run_multi_threaded(&mut self.systems, graph, world, pool);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_graph(&self) -> Result<ScheduleGraph, TecsError> {
+        let mut members = HashMap::<&str, Vec<usize>>::new();
+
+        for (index, config) in self.systems.iter().enumerate() {
+            if let Some(label) = &config.label {
+                if self.systems[..index]
+                    .iter()
+                    .any(|other| other.label.as_ref() == Some(label))
+                {
+                    return Err(TecsError::DuplicateLabel {
+                        label: label.to_string(),
+                    });
+                }
+
+                members.entry(label).or_default().push(index);
+            }
+
+            for set in &config.sets {
+                members.entry(set).or_default().push(index);
+            }
+        }
+
+        let mut sets: Vec<_> = self.sets.iter().collect();
+        sets.sort_unstable_by_key(|&(set, _)| set);
+
+        let names = self
+            .systems
+            .iter()
+            .map(|config| &config.ordering)
+            .chain(sets.iter().map(|(_, config)| &config.ordering))
+            .flat_map(|ordering| ordering.before.iter().chain(&ordering.after))
+            .chain(sets.iter().map(|(set, _)| *set));
+
+        for name in names {
+            if !members.contains_key(&**name) {
+                return Err(TecsError::UnknownLabel {
+                    label: name.to_string(),
+                });
+            }
+        }
+
+        let members_of = |name: &str| members[name].as_slice();
+        let mut dependencies = vec![Vec::new(); self.systems.len()];
+
+        let mut add_ordering = |ordering: &Ordering, systems: &[usize]| {
+            for name in &ordering.before {
+                for &then in members_of(name) {
+                    dependencies[then].extend_from_slice(systems);
+                }
+            }
+
+            for name in &ordering.after {
+                for &then in systems {
+                    dependencies[then].extend_from_slice(members_of(name));
+                }
+            }
+        };
+
+        for (index, config) in self.systems.iter().enumerate() {
+            add_ordering(&config.ordering, &[index]);
+        }
+
+        for (set, config) in sets {
+            add_ordering(&config.ordering, members_of(set));
+        }
+
+        for list in &mut dependencies {
+            list.sort_unstable();
+            list.dedup();
+        }
+
+        let mut dependents = vec![Vec::new(); self.systems.len()];
+
+        for (index, list) in dependencies.iter().enumerate() {
+            for &dependency in list {
+                dependents[dependency].push(index);
+            }
+        }
+
+        let mut in_degree: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+        let mut ready: BinaryHeap<_> = (0..self.systems.len())
+            .filter(|&index| in_degree[index] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(self.systems.len());
+
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+
+            for &dependent in &dependents[index] {
+                in_degree[dependent] -= 1;
+
+                if in_degree[dependent] == 0 {
+                    ready.push(Reverse(dependent));
+                }
+            }
+        }
+
+        if order.len() < self.systems.len() {
+            return Err(self.cycle_error(&dependencies, &in_degree));
+        }
+
+        Ok(ScheduleGraph {
+            order,
+            dependencies,
+            dependents,
+            accesses: self
+                .systems
+                .iter()
+                .map(|config| config.system.access().clone())
+                .collect(),
+        })
+    }
+
+    /// Finds a cycle among the systems that could not be sorted, that is, the ones with
+    /// positive `in_degree`. Each of them depends on another one of them.
+    fn cycle_error(&self, dependencies: &[Vec<usize>], in_degree: &[usize]) -> TecsError {
+        let mut position = vec![None; self.systems.len()];
+        let mut path = Vec::new();
+
+        let mut index = in_degree
+            .iter()
+            .position(|&degree| degree > 0)
+            .expect("some system is not sorted");
+
+        let start = loop {
+            if let Some(start) = position[index] {
+                break start;
+            }
+
+            position[index] = Some(path.len());
+            path.push(index);
+
+            index = *dependencies[index]
+                .iter()
+                .find(|&&dependency| in_degree[dependency] > 0)
+                .expect("unsorted systems depend on other unsorted systems");
+        };
+
+        // the path follows dependencies, so it goes against the order of the systems
+        let systems = path[start..]
+            .iter()
+            .rev()
+            .map(|&index| self.systems[index].system.name().to_owned())
+            .collect();
+
+        TecsError::ScheduleCycle { systems }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{
+    access::Access,
+    change::{ChangeTicks, Tick},
+    system_param::{SystemParam, SystemParamItem},
+    World,
+};
+use std::{any::type_name, borrow::Cow, marker::PhantomData};
+
+/// The components and resources a system reads and writes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SystemAccess {
+    /// Accesses to components, including the ones of query filters.
+    pub components: Access,
+    /// Accesses to resources.
+    pub resources: Access,
+}
+
+impl SystemAccess {
+    /// Records every access of the other set.
+    pub fn extend(&mut self, other: &SystemAccess) {
+        self.components.extend(&other.components);
+        self.resources.extend(&other.resources);
+    }
+
+    /// Checks if two systems can run at the same time, see [`Access::is_compatible`].
+    pub fn is_compatible(&self, other: &SystemAccess) -> bool {
+        self.components.is_compatible(&other.components)
+            && self.resources.is_compatible(&other.resources)
+    }
+}
+
+/// The name and the accesses of a system collected from its parameters.
+#[derive(Clone, Debug)]
+pub struct SystemMeta {
+    pub(crate) name: Cow<'static, str>,
+    pub(crate) access: SystemAccess,
+}
+
+impl SystemMeta {
+    pub(crate) fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            name: name.into(),
+            access: SystemAccess::default(),
+        }
+    }
+
+    /// The name of the system.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The accesses collected so far.
+    pub fn access(&self) -> &SystemAccess {
+        &self.access
+    }
+
+    /// Records the accesses of a parameter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameter conflicts with the previous parameters of the system, e.g.
+    /// `Query<&mut T>` and `Query<&T>`.
+    pub fn add_param_access<P>(&mut self, access: SystemAccess) {
+        assert!(
+            self.access.is_compatible(&access),
+            "parameter `{}` of system `{}` conflicts with its other parameters",
+            type_name::<P>(),
+            self.name,
+        );
+
+        self.access.extend(&access);
+    }
+}
+
+/// A step of a frame that runs against the [`World`]. Usually created from a function by
+/// [`IntoSystem`].
+///
+/// # Safety
+///
+/// - `access` should list every component and resource `run_unsafe` reads or writes, since
+///   schedules run systems with compatible accesses at the same time.
+/// - `run_unsafe` should not access the world in any other way.
+///
+/// Manual implementations have to opt into the contract with `unsafe impl`:
+///
+/// ```rust,compile_fail
+/// use tecs::{World, System, SystemAccess};
+///
+/// struct Sneaky(SystemAccess);
+///
+/// impl System for Sneaky {
+///     fn name(&self) -> &str {
+///         "sneaky"
+///     }
+///
+///     fn access(&self) -> &SystemAccess {
+///         &self.0
+///     }
+///
+///     fn initialize(&mut self, _world: &mut World) {}
+///
+///     unsafe fn run_unsafe(&mut self, _world: &World) {}
+///
+///     fn apply_deferred(&mut self, _world: &mut World) {}
+/// }
+/// ```
+pub unsafe trait System: Send + 'static {
+    /// The name of the system used in diagnostics.
+    fn name(&self) -> &str;
+
+    /// The components and resources the system accesses. Valid after
+    /// [`System::initialize`].
+    fn access(&self) -> &SystemAccess;
+
+    /// Prepares the system to run against the world. Does nothing if the system is already
+    /// initialized.
+    fn initialize(&mut self, world: &mut World);
+
+    /// Runs the system without applying the deferred changes, see
+    /// [`System::apply_deferred`].
+    ///
+    /// # Safety
+    ///
+    /// - the system should be initialized with the same world.
+    /// - systems running at the same time should have compatible accesses, see
+    ///   [`SystemAccess::is_compatible`].
+    unsafe fn run_unsafe(&mut self, world: &World);
+
+    /// Applies the changes deferred by the previous runs, e.g. the ones made with
+    /// [`Commands`](crate::Commands).
+    fn apply_deferred(&mut self, world: &mut World);
+
+    /// Checks if [`System::apply_deferred`] may change the world. Schedules only wait for such
+    /// systems to apply their changes.
+    fn has_deferred(&self) -> bool {
+        true
+    }
+
+    /// Initializes the system if needed, runs it and applies its deferred changes.
+    fn run(&mut self, world: &mut World) {
+        self.initialize(world);
+
+        // Safety: the system is initialized and the world is borrowed mutably
+        unsafe { self.run_unsafe(world) };
+
+        self.apply_deferred(world);
+    }
+}
+
+/// Conversion into a [`System`]. Implemented for systems and for functions whose arguments are
+/// [`SystemParam`]s, such as [`Query`](crate::Query), [`Res`](crate::Res),
+/// [`ResMut`](crate::ResMut), [`Commands`](crate::Commands) and
+/// [`EventReader`](crate::EventReader). `Marker` only tells the implementations apart.
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, Resource, Query, Res, IntoSystem, System};
+///
+/// struct Position(f32);
+/// impl Component for Position {}
+///
+/// struct Velocity(f32);
+/// impl Component for Velocity {}
+///
+/// struct DeltaTime(f32);
+/// impl Resource for DeltaTime {}
+///
+/// fn movement(mut query: Query<(&mut Position, &Velocity)>, time: Res<DeltaTime>) {
+///     for (mut position, velocity) in &mut query {
+///         position.0 += velocity.0 * time.0;
+///     }
+/// }
+///
+/// let mut world = World::new();
+///
+/// let id = world.spawn((Position(0.0), Velocity(2.0)));
+/// world.insert_resource(DeltaTime(0.5));
+///
+/// let mut system = movement.into_system();
+/// system.run(&mut world);
+/// system.run(&mut world);
+///
+/// assert_eq!(world.get::<Position>(id).unwrap().0, 2.0);
+/// ```
+pub trait IntoSystem<Marker> {
+    /// The resulting system.
+    type System: System;
+
+    /// Converts into the system.
+    fn into_system(self) -> Self::System;
+}
+
+/// Marks the implementation of [`IntoSystem`] for systems themselves.
+pub struct IsSystem;
+
+impl<S: System> IntoSystem<IsSystem> for S {
+    type System = S;
+
+    fn into_system(self) -> Self::System {
+        self
+    }
+}
+
+/// A function that can be run as a system, see [`IntoSystem`]. `Marker` is the type of the
+/// function pointer with the same parameters.
+pub trait SystemParamFunction<Marker>: Send + 'static {
+    /// The parameters of the function as a tuple.
+    type Param: SystemParam;
+
+    /// Calls the function.
+    fn call(&mut self, param: SystemParamItem<'_, Self::Param>);
+}
+
+/// A [`System`] made of a function, see [`IntoSystem`].
+pub struct FunctionSystem<Marker, F: SystemParamFunction<Marker>> {
+    function: F,
+    meta: SystemMeta,
+    state: Option<<F::Param as SystemParam>::State>,
+    last_run: Tick,
+    _marker: PhantomData<fn() -> Marker>,
+}
+
+/// Marks the implementation of [`IntoSystem`] for functions.
+pub struct IsFunctionSystem;
+
+impl<Marker: 'static, F: SystemParamFunction<Marker>> IntoSystem<(IsFunctionSystem, Marker)> for F {
+    type System = FunctionSystem<Marker, F>;
+
+    fn into_system(self) -> Self::System {
+        FunctionSystem {
+            function: self,
+            meta: SystemMeta::new(type_name::<F>()),
+            state: None,
+            last_run: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+unsafe impl<Marker: 'static, F: SystemParamFunction<Marker>> System for FunctionSystem<Marker, F> {
+    fn name(&self) -> &str {
+        self.meta.name()
+    }
+
+    fn access(&self) -> &SystemAccess {
+        self.meta.access()
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        if self.state.is_none() {
+            self.state = Some(F::Param::init(world, &mut self.meta));
+        }
+    }
+
+    unsafe fn run_unsafe(&mut self, world: &World) {
+        let state = self
+            .state
+            .as_mut()
+            .unwrap_or_else(|| panic!("system `{}` is not initialized", self.meta.name));
+
+        let ticks = ChangeTicks {
+            last_run: self.last_run,
+            this_run: world.increment_change_tick(),
+        };
+
+        // Safety: the accesses of the parameters are declared in `meta`
+        let param = unsafe { F::Param::get(state, world, ticks) };
+        self.function.call(param);
+
+        self.last_run = ticks.this_run;
+    }
+
+    fn apply_deferred(&mut self, world: &mut World) {
+        if let Some(state) = &mut self.state {
+            F::Param::apply(state, world);
+        }
+    }
+
+    fn has_deferred(&self) -> bool {
+        F::Param::HAS_DEFERRED
+    }
+}
+
+macro_rules! impl_system_param_function {
+    ( $( $p:ident : $P:ident ),* ) => {
+        impl<Func, $( $P: SystemParam, )* > SystemParamFunction<fn( $( $P, )* )> for Func
+        where
+            Func: Send + 'static,
+            for<'a> &'a mut Func: FnMut( $( $P, )* ) + FnMut( $( SystemParamItem<'_, $P>, )* ),
+        {
+            type Param = ( $( $P, )* );
+
+            #[allow(clippy::unused_unit)]
+            fn call(&mut self, param: SystemParamItem<'_, Self::Param>) {
+                // Calling through a generic function makes the compiler pick the right `FnMut`
+                // implementation of `&mut Func`
+                #[allow(clippy::too_many_arguments)]
+                fn call_inner< $( $P, )* >(mut function: impl FnMut( $( $P, )* ), $( $p: $P, )* ) {
+                    function( $( $p, )* );
+                }
+
+                let ( $( $p, )* ) = param;
+                call_inner(self, $( $p, )* );
+            }
+        }
+    };
+}
+
+impl_system_param_function! {}
+impl_system_param_function! { a: A }
+impl_system_param_function! { a: A, b: B }
+impl_system_param_function! { a: A, b: B, c: C }
+impl_system_param_function! { a: A, b: B, c: C, d: D }
+impl_system_param_function! { a: A, b: B, c: C, d: D, e: E }
+impl_system_param_function! { a: A, b: B, c: C, d: D, e: E, f: F }
+impl_system_param_function! { a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_system_param_function! { a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
@@ -0,0 +1,453 @@
+use crate::{
+    access::Access,
+    change::ChangeTicks,
+    command::CommandBuffer,
+    event::{Event, EventCursor, EventReader, Events},
+    query::{self, Fetch, Filter, QueryIter, ReadOnlyFetch},
+    system::{SystemAccess, SystemMeta},
+    ComponentSet, EntityId, Resource, TecsError, World,
+};
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+/// A value a [`System`](crate::System) fetches from the [`World`] before each run, such as
+/// [`Query`], [`Res`], [`ResMut`], [`Commands`], [`EventReader`] and tuples of them.
+///
+/// # Safety
+///
+/// - `init` should record every access `get` performs through `meta`.
+/// - `get` should not access the world in any other way.
+pub unsafe trait SystemParam {
+    /// Data the parameter keeps between runs of the system.
+    type State: Send + 'static;
+
+    /// The value passed to the system.
+    type Item<'w>;
+
+    /// Whether [`SystemParam::apply`] may change the world.
+    const HAS_DEFERRED: bool = false;
+
+    /// Records the accesses of the parameter and creates its state.
+    fn init(world: &mut World, meta: &mut SystemMeta) -> Self::State;
+
+    /// Fetches the parameter.
+    ///
+    /// # Safety
+    ///
+    /// The accesses recorded by `init` should not conflict with other borrows of the world.
+    unsafe fn get<'w>(
+        state: &'w mut Self::State,
+        world: &'w World,
+        ticks: ChangeTicks,
+    ) -> Self::Item<'w>;
+
+    /// Applies the changes deferred during the runs of the system.
+    fn apply(_state: &mut Self::State, _world: &mut World) {}
+}
+
+/// The value of a [`SystemParam`] `P` borrowed for `'w`.
+pub type SystemParamItem<'w, P> = <P as SystemParam>::Item<'w>;
+
+/// A query as a system parameter. Yields the same items as [`World::query_mut_filtered`], while
+/// [`Added`](crate::Added) and [`Changed`](crate::Changed) report changes since the previous run
+/// of the system.
+///
+/// # Panics
+///
+/// The system panics on initialization if the query accesses some component mutably more than
+/// once or conflicts with other parameters of the system.
+pub struct Query<'w, Q: Fetch, F: Filter = ()> {
+    world: &'w World,
+    ticks: ChangeTicks,
+    _query: PhantomData<fn() -> (Q, F)>,
+}
+
+impl<'w, Q: Fetch, F: Filter> Query<'w, Q, F> {
+    /// Iterates over the query results.
+    pub fn iter(&self) -> QueryIter<'_, Q, F>
+    where
+        Q: ReadOnlyFetch,
+    {
+        // Safety: `Q` only reads components
+        unsafe { QueryIter::new(&self.world.archetypes, self.ticks) }
+    }
+
+    /// Iterates over the query results possibly modifying them.
+    pub fn iter_mut(&mut self) -> QueryIter<'_, Q, F> {
+        // Safety: the query is borrowed mutably and the accesses are checked by the system
+        unsafe { QueryIter::new(&self.world.archetypes, self.ticks) }
+    }
+
+    /// Calls `f` on every query result using several threads, see [`QueryIter::par_for_each`].
+    pub fn par_for_each<'q>(&'q self, f: impl Fn(Q::Item<'q>) + Sync)
+    where
+        Q: ReadOnlyFetch,
+        Q::Item<'q>: Send,
+    {
+        self.iter().par_for_each(f);
+    }
+
+    /// Calls `f` on every query result possibly modifying them using several threads, see
+    /// [`QueryIter::par_for_each`].
+    pub fn par_for_each_mut<'q>(&'q mut self, f: impl Fn(Q::Item<'q>) + Sync)
+    where
+        Q::Item<'q>: Send,
+    {
+        self.iter_mut().par_for_each(f);
+    }
+
+    /// Fetches the query item of a single entity. Fails if the entity does not exist or does
+    /// not match the query.
+    pub fn get(&self, id: EntityId) -> Result<Q::Item<'_>, TecsError>
+    where
+        Q: ReadOnlyFetch,
+    {
+        // Safety: `Q` only reads components
+        unsafe { self.fetch(id) }
+    }
+
+    /// Fetches the query item of a single entity possibly modifying it, see [`Query::get`].
+    pub fn get_mut(&mut self, id: EntityId) -> Result<Q::Item<'_>, TecsError> {
+        // Safety: the query is borrowed mutably
+        unsafe { self.fetch(id) }
+    }
+
+    /// # Safety
+    ///
+    /// The returned item should not alias other borrows of the entity.
+    unsafe fn fetch<'q>(&self, id: EntityId) -> Result<Q::Item<'q>, TecsError> {
+        let location = self.world.location(id).ok_or(TecsError::NoSuchEntity(id))?;
+        let archetype = &self.world.archetypes[location.archetype_index as usize];
+        let entity_index = location.entity_index as usize;
+
+        let mismatch = || TecsError::MissingComponent {
+            entity: id,
+            component: std::any::type_name::<(Q, F)>(),
+        };
+
+        if !Q::matches(archetype) || !F::matches(archetype) {
+            return Err(mismatch());
+        }
+
+        if !unsafe { F::filter(F::prepare(archetype, self.ticks), entity_index) } {
+            return Err(mismatch());
+        }
+
+        let state = Q::prepare(archetype, self.ticks);
+
+        Ok(unsafe { Q::fetch(state, entity_index) })
+    }
+}
+
+impl<'q, Q: ReadOnlyFetch, F: Filter> IntoIterator for &'q Query<'_, Q, F> {
+    type Item = Q::Item<'q>;
+    type IntoIter = QueryIter<'q, Q, F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'q, Q: Fetch, F: Filter> IntoIterator for &'q mut Query<'_, Q, F> {
+    type Item = Q::Item<'q>;
+    type IntoIter = QueryIter<'q, Q, F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+unsafe impl<Q: Fetch + 'static, F: Filter> SystemParam for Query<'_, Q, F> {
+    type State = ();
+    type Item<'w> = Query<'w, Q, F>;
+
+    fn init(_world: &mut World, meta: &mut SystemMeta) {
+        query::assert_unique_access::<Q>();
+
+        let mut components = Access::default();
+        Q::access(&mut components);
+        F::access(&mut components);
+
+        meta.add_param_access::<Self>(SystemAccess {
+            components,
+            ..Default::default()
+        });
+    }
+
+    unsafe fn get<'w>(_state: &'w mut (), world: &'w World, ticks: ChangeTicks) -> Self::Item<'w> {
+        Query {
+            world,
+            ticks,
+            _query: PhantomData,
+        }
+    }
+}
+
+/// Shared access to a resource as a system parameter.
+///
+/// # Panics
+///
+/// The system panics if the resource does not exist when it runs.
+pub struct Res<'w, R: Resource> {
+    value: &'w R,
+}
+
+impl<R: Resource> Deref for Res<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+unsafe impl<R: Resource> SystemParam for Res<'_, R> {
+    type State = ();
+    type Item<'w> = Res<'w, R>;
+
+    fn init(_world: &mut World, meta: &mut SystemMeta) {
+        let mut resources = Access::default();
+        resources.add_read::<R>();
+
+        meta.add_param_access::<Self>(SystemAccess {
+            resources,
+            ..Default::default()
+        });
+    }
+
+    unsafe fn get<'w>(_state: &'w mut (), world: &'w World, _ticks: ChangeTicks) -> Self::Item<'w> {
+        let ptr = resource_ptr::<R>(world);
+
+        // Safety: the resource is only read by systems running at the same time
+        Res {
+            value: unsafe { &*ptr },
+        }
+    }
+}
+
+/// Exclusive access to a resource as a system parameter.
+///
+/// # Panics
+///
+/// The system panics if the resource does not exist when it runs.
+pub struct ResMut<'w, R: Resource> {
+    value: &'w mut R,
+}
+
+impl<R: Resource> Deref for ResMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<R: Resource> DerefMut for ResMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value
+    }
+}
+
+unsafe impl<R: Resource> SystemParam for ResMut<'_, R> {
+    type State = ();
+    type Item<'w> = ResMut<'w, R>;
+
+    fn init(_world: &mut World, meta: &mut SystemMeta) {
+        let mut resources = Access::default();
+        resources.add_write::<R>();
+
+        meta.add_param_access::<Self>(SystemAccess {
+            resources,
+            ..Default::default()
+        });
+    }
+
+    unsafe fn get<'w>(_state: &'w mut (), world: &'w World, _ticks: ChangeTicks) -> Self::Item<'w> {
+        let ptr = resource_ptr::<R>(world);
+
+        // Safety: no other system running at the same time accesses the resource
+        ResMut {
+            value: unsafe { &mut *ptr },
+        }
+    }
+}
+
+fn resource_ptr<R: Resource>(world: &World) -> *mut R {
+    world
+        .resources
+        .get_ptr::<R>()
+        .unwrap_or_else(|| panic!("{}", TecsError::missing_resource::<R>()))
+}
+
+/// Records structural changes to the world as a system parameter. The changes are applied after
+/// the system runs, see [`System::apply_deferred`](crate::System::apply_deferred). Spawned
+/// entities get their ids immediately, see [`World::reserve_entity`].
+///
+/// # Example
+///
+/// ```rust
+/// use tecs::{World, Component, Commands, Query, EntityId, IntoSystem, System};
+///
+/// struct Health(u32);
+/// impl Component for Health {}
+///
+/// struct Corpse;
+/// impl Component for Corpse {}
+///
+/// fn die(mut commands: Commands, query: Query<(EntityId, &Health)>) {
+///     for (id, health) in &query {
+///         if health.0 == 0 {
+///             commands.despawn(id);
+///             commands.spawn(Corpse);
+///         }
+///     }
+/// }
+///
+/// let mut world = World::new();
+///
+/// world.spawn(Health(0));
+/// world.spawn(Health(10));
+///
+/// die.into_system().run(&mut world);
+///
+/// assert_eq!(world.query::<&Health>().count(), 1);
+/// assert_eq!(world.query::<&Corpse>().count(), 1);
+/// ```
+pub struct Commands<'w> {
+    world: &'w World,
+    buffer: &'w mut CommandBuffer,
+}
+
+impl Commands<'_> {
+    /// Records spawning of an entity with given components and returns its id.
+    pub fn spawn<S: ComponentSet>(&mut self, set: S) -> EntityId {
+        let id = self.world.reserve_entity();
+        self.buffer.insert(id, set);
+        id
+    }
+
+    /// Records despawning of an entity, see [`World::despawn`].
+    pub fn despawn(&mut self, id: EntityId) {
+        self.buffer.despawn(id);
+    }
+
+    /// Records insertion of components into an entity, see [`World::insert`].
+    pub fn insert<S: ComponentSet>(&mut self, id: EntityId, set: S) {
+        self.buffer.insert(id, set);
+    }
+
+    /// Records removal of components from an entity, see [`World::remove`].
+    pub fn remove<S: ComponentSet>(&mut self, id: EntityId) {
+        self.buffer.remove::<S>(id);
+    }
+
+    /// The underlying command buffer.
+    pub fn buffer(&mut self) -> &mut CommandBuffer {
+        self.buffer
+    }
+}
+
+unsafe impl SystemParam for Commands<'_> {
+    type State = CommandBuffer;
+    type Item<'w> = Commands<'w>;
+
+    const HAS_DEFERRED: bool = true;
+
+    fn init(_world: &mut World, _meta: &mut SystemMeta) -> Self::State {
+        CommandBuffer::new()
+    }
+
+    unsafe fn get<'w>(
+        state: &'w mut Self::State,
+        world: &'w World,
+        _ticks: ChangeTicks,
+    ) -> Self::Item<'w> {
+        Commands {
+            world,
+            buffer: state,
+        }
+    }
+
+    fn apply(state: &mut Self::State, world: &mut World) {
+        world.apply(state);
+    }
+}
+
+/// Reads the events sent since the previous run of the system. The cursor is kept between runs.
+///
+/// # Panics
+///
+/// The system panics if the events are not added when it runs, see [`World::add_event`].
+unsafe impl<E: Event> SystemParam for EventReader<'_, E> {
+    type State = EventCursor<E>;
+    type Item<'w> = EventReader<'w, E>;
+
+    fn init(_world: &mut World, meta: &mut SystemMeta) -> Self::State {
+        let mut resources = Access::default();
+        resources.add_read::<Events<E>>();
+
+        meta.add_param_access::<Self>(SystemAccess {
+            resources,
+            ..Default::default()
+        });
+
+        EventCursor::new()
+    }
+
+    unsafe fn get<'w>(
+        state: &'w mut Self::State,
+        world: &'w World,
+        _ticks: ChangeTicks,
+    ) -> Self::Item<'w> {
+        let ptr = resource_ptr::<Events<E>>(world);
+
+        // Safety: the events are only read by systems running at the same time
+        state.reader(unsafe { &*ptr })
+    }
+}
+
+macro_rules! impl_system_param {
+    ( $( $s:ident : $P:ident ),* ) => {
+        #[allow(clippy::unused_unit)]
+        unsafe impl< $( $P: SystemParam, )* > SystemParam for ( $( $P, )* ) {
+            type State = ( $( $P::State, )* );
+            type Item<'w> = ( $( $P::Item<'w>, )* );
+
+            const HAS_DEFERRED: bool = false $( || $P::HAS_DEFERRED )*;
+
+            #[allow(unused_variables)]
+            fn init(world: &mut World, meta: &mut SystemMeta) -> Self::State {
+                ( $( $P::init(world, meta), )* )
+            }
+
+            #[allow(unused_variables)]
+            unsafe fn get<'w>(
+                state: &'w mut Self::State,
+                world: &'w World,
+                ticks: ChangeTicks,
+            ) -> Self::Item<'w> {
+                let ( $( $s, )* ) = state;
+
+                ( $( unsafe { $P::get($s, world, ticks) }, )* )
+            }
+
+            #[allow(unused_variables)]
+            fn apply(state: &mut Self::State, world: &mut World) {
+                let ( $( $s, )* ) = state;
+
+                $( $P::apply($s, world); )*
+            }
+        }
+    };
+}
+
+impl_system_param! {}
+impl_system_param! { a: A }
+impl_system_param! { a: A, b: B }
+impl_system_param! { a: A, b: B, c: C }
+impl_system_param! { a: A, b: B, c: C, d: D }
+impl_system_param! { a: A, b: B, c: C, d: D, e: E }
+impl_system_param! { a: A, b: B, c: C, d: D, e: E, f: F }
+impl_system_param! { a: A, b: B, c: C, d: D, e: E, f: F, g: G }
+impl_system_param! { a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H }
@@ -1,8 +1,21 @@
 use crate::{
-    query::{Query, QueryMut},
-    archetype::Archetype, component_set::ComponentSet, EntityId, Location,
+    archetype::{Archetype, TypeInfo},
+    change::{ChangeTicks, Mut, Tick},
+    chunk::{ChunkFetch, QueryChunks},
+    command::CommandBuffer,
+    component_set::{ComponentSet, N_STACK_TYPE_IDS},
+    event::{Event, Events},
+    query::{self, Fetch, Filter, QueryIter, ReadOnlyFetch},
+    removal::{RemovalRetention, Removals},
+    resource::{Resource, Resources},
+    EntityId, Location, TecsError,
+};
+use smallvec::SmallVec;
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    sync::atomic::{AtomicIsize, AtomicU64, Ordering},
 };
-use std::{any::TypeId, collections::HashMap};
 
 /// An ECS world. The place where each component and entity are stored.
 ///
@@ -29,14 +42,47 @@ use std::{any::TypeId, collections::HashMap};
 /// assert_eq!(entity.get::<Name>(), Some(&Name("Marcus")));
 /// assert_eq!(entity.get::<Age>(), Some(&Age(21)));
 /// ```
-#[derive(Default)]
 pub struct World {
     pub(crate) archetypes: Vec<Archetype>,
-    pub(crate) locations: Vec<Location>,
+    pub(crate) locations: Vec<Option<Location>>,
+    pub(crate) generations: Vec<u32>,
+    pub(crate) free_list: Vec<u32>,
+    /// Entities reserved through a shared reference take free slots from the end of the free list
+    /// moving the cursor down. Negative values count slots past the end of `locations`.
+    pub(crate) reserve_cursor: AtomicIsize,
     pub(crate) index: HashMap<Box<[TypeId]>, usize>,
+    pub(crate) resources: Resources,
+    pub(crate) change_tick: AtomicU64,
+    pub(crate) last_change_tick: Tick,
+    pub(crate) removals: Removals,
+    /// Updates the event queues added by [`World::add_event`], keyed by the type of the event.
+    pub(crate) event_updaters: HashMap<TypeId, fn(&mut World)>,
+}
+
+/// The index of the archetype without components. Every entity is spawned by an insertion into it.
+pub(crate) const EMPTY_ARCHETYPE_INDEX: usize = 0;
+
+impl Default for World {
+    fn default() -> Self {
+        Self {
+            archetypes: vec![Archetype::new([].into())],
+            locations: vec![],
+            generations: vec![],
+            free_list: vec![],
+            reserve_cursor: AtomicIsize::new(0),
+            index: HashMap::from([(Box::default(), EMPTY_ARCHETYPE_INDEX)]),
+            resources: Resources::default(),
+            change_tick: AtomicU64::new(1),
+            last_change_tick: 0,
+            removals: Removals::default(),
+            event_updaters: HashMap::new(),
+        }
+    }
 }
 
-/// Signifies that given type can be used as a component.
+/// Signifies that given type can be used as a component. Components should be [`Send`] and
+/// [`Sync`] so that the [`World`] is too. Types that are not thread-safe can be stored as
+/// non-send components of a [`LocalWorld`](crate::LocalWorld).
 ///
 /// # Example
 ///
@@ -49,11 +95,11 @@ pub struct World {
 /// struct CanFly;
 /// impl Component for CanFly {}
 ///
-/// // .. or any type that is `Sized` and `'static`
+/// // .. or any type that is `Sized`, `Send`, `Sync` and `'static`
 /// struct Velocity([f32; 3]);
 /// impl Component for Velocity {}
 /// ```
-pub trait Component: Sized + 'static {}
+pub trait Component: Sized + Send + Sync + 'static {}
 
 impl World {
     /// Constructs new empty world.
@@ -82,36 +128,690 @@ impl World {
     /// let id = world.spawn((CanFly, CanJump));
     /// ```
     pub fn spawn<S: ComponentSet>(&mut self, set: S) -> EntityId {
-        let entity = self.locations.len() as EntityId;
+        let archetype_index = self.insert_target::<S>(EMPTY_ARCHETYPE_INDEX);
 
-        let archetype_index = match S::get_index(&self.index) {
-            Some(index) => index,
-            None => {
-                let archetype = S::make_archetype();
-                let index = self.archetypes.len();
+        unsafe { self.spawn_in(archetype_index, set) }
+    }
 
-                self.index.insert(S::component_ids(), index);
-                self.archetypes.push(archetype);
+    /// Fallible version of [`World::spawn`]. Fails if there is no memory for the entity, in which
+    /// case the components are dropped.
+    pub fn try_spawn<S: ComponentSet>(&mut self, set: S) -> Result<EntityId, TecsError> {
+        let archetype_index = self.insert_target::<S>(EMPTY_ARCHETYPE_INDEX);
 
-                index
-            }
-        };
+        self.archetypes[archetype_index].try_reserve(1)?;
+
+        Ok(unsafe { self.spawn_in(archetype_index, set) })
+    }
+
+    /// Reserves storage for at least `additional` more entities with components `S`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows. Aborts if the allocation fails.
+    pub fn reserve<S: ComponentSet>(&mut self, additional: usize) {
+        if let Err(error) = self.try_reserve::<S>(additional) {
+            error.handle_alloc();
+        }
+    }
+
+    /// Fallible version of [`World::reserve`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component, TecsError};
+    ///
+    /// struct Particle([f32; 3]);
+    /// impl Component for Particle {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// assert_eq!(world.try_reserve::<Particle>(1024), Ok(()));
+    /// assert_eq!(
+    ///     world.try_reserve::<Particle>(usize::MAX),
+    ///     Err(TecsError::CapacityOverflow),
+    /// );
+    /// ```
+    pub fn try_reserve<S: ComponentSet>(&mut self, additional: usize) -> Result<(), TecsError> {
+        let archetype_index = self.insert_target::<S>(EMPTY_ARCHETYPE_INDEX);
+
+        self.archetypes[archetype_index].try_reserve(additional)
+    }
 
-        let entity_index = self.archetypes[archetype_index].entities.len();
+    /// Spawns entities with components from the iterator and returns their ids. The archetype
+    /// of the components is resolved only once and the storage is reserved upfront using the
+    /// iterator's `size_hint`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Particle(f32);
+    /// impl Component for Particle {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let ids: Vec<_> = world
+    ///     .spawn_batch((0..1000).map(|i| Particle(i as f32)))
+    ///     .collect();
+    ///
+    /// assert_eq!(ids.len(), 1000);
+    /// assert_eq!(world.get::<Particle>(ids[42]), Some(&Particle(42.0)));
+    /// ```
+    pub fn spawn_batch<S, I>(&mut self, sets: I) -> impl ExactSizeIterator<Item = EntityId>
+    where
+        S: ComponentSet,
+        I: IntoIterator<Item = S>,
+    {
+        let sets = sets.into_iter();
+        let (additional, _) = sets.size_hint();
+
+        let archetype_index = self.insert_target::<S>(EMPTY_ARCHETYPE_INDEX);
+
+        self.archetypes[archetype_index].reserve(additional);
+        self.locations
+            .reserve(additional.saturating_sub(self.free_list.len()));
+
+        let mut ids = Vec::with_capacity(additional);
+
+        for set in sets {
+            ids.push(unsafe { self.spawn_in(archetype_index, set) });
+        }
+
+        ids.into_iter()
+    }
+
+    /// Spawns an entity into the archetype `archetype_index`.
+    ///
+    /// # Safety
+    ///
+    /// The archetype should contain exactly the components of `S`.
+    unsafe fn spawn_in<S: ComponentSet>(&mut self, archetype_index: usize, set: S) -> EntityId {
+        let entity = self.alloc_entity();
+        let archetype = &mut self.archetypes[archetype_index];
+
+        archetype.reserve(1);
+
+        let entity_index = archetype.entities.len();
         let location = Location {
             entity_index: entity_index as u32,
             archetype_index: archetype_index as u32,
         };
 
-        self.locations.push(location);
+        self.locations[entity.index as usize] = Some(location);
         unsafe {
-            set.write_archetype(&mut self.archetypes[archetype_index]);
+            set.write_archetype(archetype, entity_index);
+        }
+        archetype.push_entity(entity, *self.change_tick.get_mut());
+
+        entity
+    }
+
+    /// Adds components to an existing entity moving it to another archetype if needed. Components
+    /// that the entity already has are replaced. Returns `false` if there is no such entity in the
+    /// world.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Health(u32);
+    /// impl Component for Health {}
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Stunned;
+    /// impl Component for Stunned {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let id = world.spawn(Health(100));
+    /// world.insert(id, (Stunned, Health(90)));
+    ///
+    /// assert_eq!(world.get::<Health>(id), Some(&Health(90)));
+    /// assert_eq!(world.query::<&Stunned>().count(), 1);
+    /// ```
+    pub fn insert<S: ComponentSet>(&mut self, id: EntityId, set: S) -> bool {
+        self.flush();
+
+        let Some(location) = self.location(id) else {
+            return false;
+        };
+
+        let source_index = location.archetype_index as usize;
+        let entity_index = location.entity_index as usize;
+
+        let tick = *self.change_tick.get_mut();
+        let infos = S::component_infos();
+
+        let target_index = self.insert_target::<S>(source_index);
+        let source = &mut self.archetypes[source_index];
+
+        for info in infos.as_ref() {
+            // Safety: the component is overwritten below
+            unsafe { source.drop_component(info.id, entity_index) };
+        }
+
+        if target_index == source_index {
+            unsafe { set.write_archetype(source, entity_index) };
+
+            for info in infos.as_ref() {
+                source.set_added(info.id, entity_index, tick);
+            }
+
+            return true;
+        }
+
+        let (source, target) = self.archetypes_pair_mut(source_index, target_index);
+
+        let (target_entity_index, moved) = unsafe { source.move_to(entity_index, target, tick) };
+        unsafe { set.write_archetype(target, target_entity_index) };
+
+        for info in infos.as_ref() {
+            target.set_added(info.id, target_entity_index, tick);
+        }
+
+        self.relocate(id, moved, location, target_index, target_entity_index);
+
+        true
+    }
+
+    /// Removes components from an existing entity moving it to another archetype and returns
+    /// them. Returns `None` if there is no such entity or it lacks some of the components.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Position(f32);
+    /// impl Component for Position {}
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Velocity(f32);
+    /// impl Component for Velocity {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let id = world.spawn((Position(0.0), Velocity(1.0)));
+    ///
+    /// assert_eq!(world.remove::<Velocity>(id), Some(Velocity(1.0)));
+    /// assert_eq!(world.remove::<Velocity>(id), None);
+    /// assert_eq!(world.get::<Position>(id), Some(&Position(0.0)));
+    /// ```
+    pub fn remove<S: ComponentSet>(&mut self, id: EntityId) -> Option<S> {
+        self.flush();
+
+        let location = self.location(id)?;
+
+        let source_index = location.archetype_index as usize;
+        let entity_index = location.entity_index as usize;
+
+        let tick = *self.change_tick.get_mut();
+        let target_index = self.remove_target::<S>(source_index)?;
+        let (source, target) = self.archetypes_pair_mut(source_index, target_index);
+
+        let set = unsafe { S::read_archetype(source, entity_index) };
+        let (target_entity_index, moved) = unsafe { source.move_to(entity_index, target, tick) };
+
+        self.relocate(id, moved, location, target_index, target_entity_index);
+
+        for info in S::component_infos().as_ref() {
+            self.removals.push(info.id, id, tick);
+        }
+
+        Some(set)
+    }
+
+    /// Updates locations after the entity `id` has been moved from `location` to the row
+    /// `entity_index` of the archetype `archetype_index` and `moved` took its place.
+    fn relocate(
+        &mut self,
+        id: EntityId,
+        moved: Option<EntityId>,
+        location: Location,
+        archetype_index: usize,
+        entity_index: usize,
+    ) {
+        if let Some(moved) = moved {
+            self.locations[moved.index as usize] = Some(location);
+        }
+
+        self.locations[id.index as usize] = Some(Location {
+            entity_index: entity_index as u32,
+            archetype_index: archetype_index as u32,
+        });
+    }
+
+    /// Gives an index of the archetype the entities of the archetype `source_index` move to after
+    /// inserting components `S`. The result is cached in the archetype graph.
+    fn insert_target<S: ComponentSet>(&mut self, source_index: usize) -> usize {
+        let set_id = TypeId::of::<S>();
+
+        if let Some(&index) = self.archetypes[source_index].insert_edges.get(&set_id) {
+            return index;
+        }
+
+        let infos = S::component_infos();
+        let source = &self.archetypes[source_index];
+
+        let mut types: SmallVec<[TypeInfo; N_STACK_TYPE_IDS]> = source
+            .component_types
+            .iter()
+            .chain(infos.as_ref())
+            .cloned()
+            .collect();
+
+        types.sort_unstable();
+        types.dedup();
+
+        let is_disjoint = types.len() == source.component_types.len() + S::COMPONENT_COUNT;
+        let target_index = self.get_or_insert_archetype(&types);
+
+        self.archetypes[source_index]
+            .insert_edges
+            .insert(set_id, target_index);
+
+        if is_disjoint {
+            self.archetypes[target_index]
+                .remove_edges
+                .insert(set_id, source_index);
+        }
+
+        target_index
+    }
+
+    /// Gives an index of the archetype the entities of the archetype `source_index` move to after
+    /// removing components `S`. Returns `None` if the archetype lacks some of the components. The
+    /// result is cached in the archetype graph.
+    fn remove_target<S: ComponentSet>(&mut self, source_index: usize) -> Option<usize> {
+        let set_id = TypeId::of::<S>();
+
+        if let Some(&index) = self.archetypes[source_index].remove_edges.get(&set_id) {
+            return Some(index);
         }
-        self.archetypes[archetype_index].entities.push(entity);
+
+        let infos = S::component_infos();
+        let infos = infos.as_ref();
+        let source = &self.archetypes[source_index];
+
+        if !infos.iter().all(|info| source.index.contains_key(&info.id)) {
+            return None;
+        }
+
+        let types: SmallVec<[TypeInfo; N_STACK_TYPE_IDS]> = source
+            .component_types
+            .iter()
+            .filter(|&info| !infos.contains(info))
+            .cloned()
+            .collect();
+
+        let target_index = self.get_or_insert_archetype(&types);
+
+        self.archetypes[source_index]
+            .remove_edges
+            .insert(set_id, target_index);
+        self.archetypes[target_index]
+            .insert_edges
+            .insert(set_id, source_index);
+
+        Some(target_index)
+    }
+
+    /// Gives an index of the archetype with given component types sorted by their ids. Creates
+    /// the archetype if there is no such one.
+    fn get_or_insert_archetype(&mut self, types: &[TypeInfo]) -> usize {
+        let ids: SmallVec<[TypeId; N_STACK_TYPE_IDS]> = types.iter().map(|info| info.id).collect();
+
+        if let Some(&index) = self.index.get(&ids[..]) {
+            return index;
+        }
+
+        let index = self.archetypes.len();
+
+        self.index.insert(ids.into_iter().collect(), index);
+        self.archetypes.push(Archetype::new(types.into()));
+
+        index
+    }
+
+    /// Borrows two distinct archetypes mutably.
+    fn archetypes_pair_mut(
+        &mut self,
+        first: usize,
+        second: usize,
+    ) -> (&mut Archetype, &mut Archetype) {
+        assert_ne!(first, second, "can not borrow the same archetype twice");
+
+        if first < second {
+            let (head, tail) = self.archetypes.split_at_mut(second);
+            (&mut head[first], &mut tail[0])
+        } else {
+            let (head, tail) = self.archetypes.split_at_mut(first);
+            (&mut tail[0], &mut head[second])
+        }
+    }
+
+    /// Takes a free slot from the free list or makes a new one.
+    fn alloc_entity(&mut self) -> EntityId {
+        self.flush();
+
+        let entity = match self.free_list.pop() {
+            Some(index) => EntityId::new(index, self.generations[index as usize]),
+            None => {
+                let index = self.locations.len() as u32;
+
+                self.locations.push(None);
+                self.generations.push(0);
+
+                EntityId::new(index, 0)
+            }
+        };
+
+        *self.reserve_cursor.get_mut() = self.free_list.len() as isize;
 
         entity
     }
 
+    /// Reserves an entity id through a shared reference, e.g. from several threads recording
+    /// commands at once. The entity becomes alive without components the next time the world is
+    /// mutated, see [`World::flush`]. Until then [`World::contains`] returns `false` for it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component, CommandBuffer};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Bullet(f32);
+    /// impl Component for Bullet {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let buffers: Vec<_> = std::thread::scope(|scope| {
+    ///     let handles: Vec<_> = (0..4)
+    ///         .map(|i| {
+    ///             let world = &world;
+    ///             scope.spawn(move || {
+    ///                 let mut commands = CommandBuffer::new();
+    ///                 commands.insert(world.reserve_entity(), Bullet(i as f32));
+    ///                 commands
+    ///             })
+    ///         })
+    ///         .collect();
+    ///
+    ///     handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    /// });
+    ///
+    /// for mut commands in buffers {
+    ///     world.apply(&mut commands);
+    /// }
+    ///
+    /// assert_eq!(world.query::<&Bullet>().count(), 4);
+    /// ```
+    pub fn reserve_entity(&self) -> EntityId {
+        self.reserve_entities(1).next().unwrap()
+    }
+
+    /// Reserves `count` entity ids through a shared reference, see [`World::reserve_entity`].
+    pub fn reserve_entities(&self, count: u32) -> impl ExactSizeIterator<Item = EntityId> + '_ {
+        let end = self
+            .reserve_cursor
+            .fetch_sub(count as isize, Ordering::Relaxed);
+        let start = end - count as isize;
+
+        (start..end)
+            .rev()
+            .map(move |cursor| match usize::try_from(cursor) {
+                Ok(slot) => {
+                    let index = self.free_list[slot];
+                    EntityId::new(index, self.generations[index as usize])
+                }
+                Err(_) => {
+                    let index = self.locations.len() as isize - cursor - 1;
+                    let index = u32::try_from(index).expect("too many entities reserved");
+
+                    EntityId::new(index, 0)
+                }
+            })
+    }
+
+    /// Turns every reserved entity into an entity without components. Called by every method
+    /// that spawns, despawns or moves entities.
+    pub fn flush(&mut self) {
+        let cursor = *self.reserve_cursor.get_mut();
+        let n_free = self.free_list.len();
+
+        if cursor == n_free as isize {
+            return;
+        }
+
+        let first_free = cursor.max(0) as usize;
+        let n_new = cursor.min(0).unsigned_abs();
+
+        let archetype = &mut self.archetypes[EMPTY_ARCHETYPE_INDEX];
+        archetype.reserve(n_free - first_free + n_new);
+
+        let new_indices = self.locations.len()..self.locations.len() + n_new;
+
+        self.locations.resize(new_indices.end, None);
+        self.generations.resize(new_indices.end, 0);
+
+        let reused = self.free_list.drain(first_free..);
+
+        for index in reused.rev().chain(new_indices.map(|index| index as u32)) {
+            self.locations[index as usize] = Some(Location {
+                entity_index: archetype.entities.len() as u32,
+                archetype_index: EMPTY_ARCHETYPE_INDEX as u32,
+            });
+
+            archetype
+                .entities
+                .push(EntityId::new(index, self.generations[index as usize]));
+        }
+
+        *self.reserve_cursor.get_mut() = self.free_list.len() as isize;
+    }
+
+    /// Finds the location of an entity. Returns `None` for despawned entities.
+    pub(crate) fn location(&self, id: EntityId) -> Option<Location> {
+        if self.generations.get(id.index as usize) != Some(&id.generation) {
+            return None;
+        }
+
+        self.locations[id.index as usize]
+    }
+
+    /// Despawns an entity dropping all of its components. Returns `false` if there is no such
+    /// entity in the world.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// struct Projectile;
+    /// impl Component for Projectile {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let id = world.spawn(Projectile);
+    ///
+    /// assert!(world.despawn(id));
+    /// assert!(!world.despawn(id));
+    /// assert!(!world.contains(id));
+    /// ```
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        self.flush();
+
+        let Some(location) = self.location(id) else {
+            return false;
+        };
+
+        self.locations[id.index as usize] = None;
+
+        // a slot whose generation is exhausted is retired, so that stale handles never alias
+        if let Some(generation) = id.generation.checked_add(1) {
+            self.generations[id.index as usize] = generation;
+            self.free_list.push(id.index);
+        }
+
+        *self.reserve_cursor.get_mut() = self.free_list.len() as isize;
+
+        let tick = *self.change_tick.get_mut();
+        let archetype = &mut self.archetypes[location.archetype_index as usize];
+
+        for type_id in archetype.component_ids() {
+            self.removals.push(type_id, id, tick);
+        }
+
+        let moved = unsafe { archetype.swap_remove(location.entity_index as usize) };
+
+        if let Some(moved) = moved {
+            self.locations[moved.index as usize] = Some(location);
+        }
+
+        true
+    }
+
+    /// Executes the commands recorded in the buffer in order leaving it empty, see
+    /// [`CommandBuffer`].
+    pub fn apply(&mut self, buffer: &mut CommandBuffer) {
+        self.flush();
+        buffer.apply(self);
+    }
+
+    /// Checks whether the entity with given id exists in the world.
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.location(id).is_some()
+    }
+
+    /// All archetypes of the world. Archetypes are linked to each other with edges describing which
+    /// archetype an entity moves to after inserting or removing a set of components, see
+    /// [`Archetype::insert_edges`] and [`Archetype::remove_edges`]. The archetype at index `0` has
+    /// no components.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// struct Position(f32);
+    /// impl Component for Position {}
+    ///
+    /// struct Velocity(f32);
+    /// impl Component for Velocity {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let id = world.spawn(Position(0.0));
+    /// world.insert(id, Velocity(1.0));
+    ///
+    /// let archetypes = world.archetypes();
+    /// let with_position = archetypes[0].insert_edge::<Position>().unwrap();
+    /// let with_both = archetypes[with_position].insert_edge::<Velocity>().unwrap();
+    ///
+    /// assert!(archetypes[with_both].contains::<Velocity>());
+    /// assert_eq!(archetypes[with_both].remove_edge::<Velocity>(), Some(with_position));
+    /// assert_eq!(archetypes[with_both].entities(), [id]);
+    /// ```
+    pub fn archetypes(&self) -> &[Archetype] {
+        &self.archetypes
+    }
+
+    /// The current change tick of the world. Components added or accessed mutably are marked with
+    /// it.
+    pub fn change_tick(&self) -> Tick {
+        self.change_tick.load(Ordering::Relaxed)
+    }
+
+    /// The change tick at the moment of the last [`World::clear_trackers`] call.
+    pub fn last_change_tick(&self) -> Tick {
+        self.last_change_tick
+    }
+
+    /// Advances the change tick of the world returning its previous value.
+    pub fn increment_change_tick(&self) -> Tick {
+        self.change_tick.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Forgets the changes made so far, so that [`Added`](crate::Added) and
+    /// [`Changed`](crate::Changed) filters of the world queries only report later ones. Usually
+    /// called once per frame.
+    pub fn clear_trackers(&mut self) {
+        self.last_change_tick = *self.change_tick.get_mut();
+        *self.change_tick.get_mut() += 1;
+
+        self.removals.start_frame(*self.change_tick.get_mut());
+    }
+
+    /// Entities that lost component `C` since the last [`World::clear_trackers`] call, either by
+    /// [`World::remove`] or by [`World::despawn`], in the order of removal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// struct Health(u32);
+    /// impl Component for Health {}
+    ///
+    /// struct Poisoned;
+    /// impl Component for Poisoned {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let cured = world.spawn((Health(50), Poisoned));
+    /// let dead = world.spawn((Health(0), Poisoned));
+    ///
+    /// world.remove::<Poisoned>(cured);
+    /// world.despawn(dead);
+    ///
+    /// assert_eq!(world.removed::<Poisoned>().collect::<Vec<_>>(), [cured, dead]);
+    /// assert_eq!(world.removed::<Health>().collect::<Vec<_>>(), [dead]);
+    ///
+    /// world.clear_trackers();
+    ///
+    /// assert_eq!(world.removed::<Poisoned>().count(), 0);
+    /// ```
+    pub fn removed<C: Component>(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.removed_since::<C>(self.last_change_tick)
+    }
+
+    /// Entities that lost component `C` after `tick`. Only removals that are still retained are
+    /// reported, see [`World::set_removal_retention`].
+    pub fn removed_since<C: Component>(&self, tick: Tick) -> impl Iterator<Item = EntityId> + '_ {
+        self.removals.since(TypeId::of::<C>(), tick)
+    }
+
+    /// Sets how long removed components are remembered. By default removals are kept for two
+    /// frames, see [`RemovalRetention`].
+    pub fn set_removal_retention(&mut self, retention: RemovalRetention) {
+        self.removals.retention = retention;
+    }
+
+    /// The current removal retention policy.
+    pub fn removal_retention(&self) -> RemovalRetention {
+        self.removals.retention
+    }
+
+    /// Forgets every removed component.
+    pub fn clear_removed(&mut self) {
+        self.removals.clear();
+    }
+
+    /// The ticks the world queries compare changes against.
+    pub(crate) fn change_ticks(&self) -> ChangeTicks {
+        ChangeTicks {
+            last_run: self.last_change_tick,
+            this_run: self.change_tick(),
+        }
+    }
+
     /// Creates an immutable query into the world. Queries can be used to fetch some specific groups of
     /// components (named archetypes).
     ///
@@ -148,14 +848,28 @@ impl World {
     ///
     /// assert_eq!(can_fly_names, [Name("Red Bird"), Name("Airplane")]);
     /// ```
-    pub fn query<'w, Q: Query<'w>>(&'w self) -> impl Iterator<Item = Q::Output> + 'w {
-        Q::query(self)
+    pub fn query<Q: ReadOnlyFetch>(&self) -> QueryIter<'_, Q> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    /// Creates an immutable query into the world restricted to entities passing the filter `F`,
+    /// see [`With`](crate::With), [`Without`](crate::Without), [`Added`](crate::Added) and
+    /// [`Changed`](crate::Changed). Changes are reported since the last
+    /// [`World::clear_trackers`] call.
+    pub fn query_filtered<Q: ReadOnlyFetch, F: Filter>(&self) -> QueryIter<'_, Q, F> {
+        // Safety: `Q` only reads components
+        unsafe { QueryIter::new(&self.archetypes, self.change_ticks()) }
     }
 
     /// Creates a mutable query into the world. Queries can be used to fetch some specific groups of
     /// components (named archetypes) and possibly update them. `query_mut`'s components are
     /// totally modifiable.
     ///
+    /// # Panics
+    ///
+    /// Panics if the query accesses some component mutably more than once, e.g.
+    /// `(&mut Name, &Name)`.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -184,7 +898,7 @@ impl World {
     /// let mut can_fly_names = vec![];
     ///
     /// // you can also opt `EntityId` out if you want
-    /// for (name, _can_fly, color) in world.query_mut::<(&Name, &CanFly, &mut Color)>() {
+    /// for (name, _can_fly, mut color) in world.query_mut::<(&Name, &CanFly, &mut Color)>() {
     ///     can_fly_names.push(name.clone());
     ///     // you can modify world through the `query_mut`
     ///     *color = Color::Green;
@@ -192,34 +906,192 @@ impl World {
     ///
     /// assert_eq!(can_fly_names, [Name("Red Bird")]);
     /// ```
-    pub fn query_mut<'w, Q>(&'w mut self) -> impl Iterator<Item = Q::Output> + 'w
-    where
-        Q: QueryMut<'w>,
-    {
-        Q::query_mut(self)
+    pub fn query_mut<Q: Fetch + 'static>(&mut self) -> QueryIter<'_, Q> {
+        self.query_mut_filtered::<Q, ()>()
+    }
+
+    /// Creates a mutable query into the world restricted to entities passing the filter `F`,
+    /// see [`World::query_filtered`].
+    pub fn query_mut_filtered<Q: Fetch + 'static, F: Filter>(&mut self) -> QueryIter<'_, Q, F> {
+        query::assert_unique_access::<Q>();
+
+        // Safety: the world is borrowed mutably and `Q` does not alias components
+        unsafe { QueryIter::new(&self.archetypes, self.change_ticks()) }
     }
 
-    /// Retrieve a component from a given entity.
+    /// Creates an immutable query yielding whole columns of every matching archetype at once,
+    /// e.g. `(&[EntityId], &[Position], &[Velocity])` for `(&Position, &Velocity)`. Useful to
+    /// hand the components to vectorized code.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// struct Position(f32);
+    /// impl Component for Position {}
+    ///
+    /// struct Frozen;
+    /// impl Component for Frozen {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// world.spawn(Position(1.0));
+    /// world.spawn((Position(2.0), Frozen));
+    ///
+    /// let sum: f32 = world
+    ///     .query_chunks::<&Position>()
+    ///     .map(|(_ids, positions)| positions.iter().map(|position| position.0).sum::<f32>())
+    ///     .sum();
+    ///
+    /// assert_eq!(sum, 3.0);
+    /// assert_eq!(world.query_chunks::<&Frozen>().count(), 1);
+    /// ```
+    pub fn query_chunks<Q: ChunkFetch + ReadOnlyFetch>(&self) -> QueryChunks<'_, Q> {
+        self.query_chunks_filtered::<Q, ()>()
+    }
+
+    /// Creates an immutable chunk query restricted to archetypes passing the filter `F`, see
+    /// [`World::query_chunks`]. Only filters checking whole archetypes, such as
+    /// [`With`](crate::With) and [`Without`](crate::Without), are allowed, which is checked at
+    /// compile time.
+    ///
+    /// ```rust,compile_fail
+    /// use tecs::{World, Component, Changed};
+    ///
+    /// struct Health(u32);
+    /// impl Component for Health {}
+    ///
+    /// let world = World::new();
+    ///
+    /// // `Changed` checks every row
+    /// for _ in world.query_chunks_filtered::<&Health, Changed<Health>>() {}
+    /// ```
+    pub fn query_chunks_filtered<Q: ChunkFetch + ReadOnlyFetch, F: Filter>(
+        &self,
+    ) -> QueryChunks<'_, Q, F> {
+        // Safety: `Q` only reads components
+        unsafe { QueryChunks::new(&self.archetypes, self.change_ticks()) }
+    }
+
+    /// Creates a mutable query yielding whole columns of every matching archetype at once, e.g.
+    /// `(&[EntityId], &[Position], &mut [Velocity])` for `(&Position, &mut Velocity)`, see
+    /// [`World::query_chunks`]. Every component of the `&mut` slices is marked changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the query accesses some component mutably more than once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// struct Position(f32);
+    /// impl Component for Position {}
+    ///
+    /// struct Velocity(f32);
+    /// impl Component for Velocity {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let moving = world.spawn((Position(1.0), Velocity(2.0)));
+    ///
+    /// for (ids, positions, velocities) in world.query_chunks_mut::<(&mut Position, &Velocity)>() {
+    ///     assert_eq!(ids.len(), positions.len());
+    ///
+    ///     for (position, velocity) in positions.iter_mut().zip(velocities) {
+    ///         position.0 += velocity.0;
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(world.get::<Position>(moving).unwrap().0, 3.0);
+    /// ```
+    pub fn query_chunks_mut<Q: ChunkFetch + 'static>(&mut self) -> QueryChunks<'_, Q> {
+        self.query_chunks_mut_filtered::<Q, ()>()
+    }
+
+    /// Creates a mutable chunk query restricted to archetypes passing the filter `F`, see
+    /// [`World::query_chunks_filtered`].
+    pub fn query_chunks_mut_filtered<Q: ChunkFetch + 'static, F: Filter>(
+        &mut self,
+    ) -> QueryChunks<'_, Q, F> {
+        query::assert_unique_access::<Q>();
+
+        // Safety: the world is borrowed mutably and `Q` does not alias components
+        unsafe { QueryChunks::new(&self.archetypes, self.change_ticks()) }
+    }
+
+    /// Retrieve a component from a given entity. Returns `None` if the entity is despawned.
     ///
     /// # Note
     ///
     /// There is more optimal way to get components from an entity, see [`World::entity`].
     pub fn get<C: Component>(&self, id: EntityId) -> Option<&C> {
-        self.entity(id).get::<C>()
+        self.try_get::<C>(id).ok()
     }
 
-    /// Retrieve a mutable reference to a component from a given entity.
+    /// Fallible version of [`World::get`] telling apart missing entities and missing components.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component, TecsError};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Health(u32);
+    /// impl Component for Health {}
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Armor(u32);
+    /// impl Component for Armor {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let id = world.spawn(Health(100));
+    ///
+    /// assert_eq!(world.try_get::<Health>(id), Ok(&Health(100)));
+    /// assert!(matches!(
+    ///     world.try_get::<Armor>(id),
+    ///     Err(TecsError::MissingComponent { .. }),
+    /// ));
+    ///
+    /// world.despawn(id);
+    ///
+    /// assert_eq!(world.try_get::<Health>(id), Err(TecsError::NoSuchEntity(id)));
+    /// ```
+    pub fn try_get<C: Component>(&self, id: EntityId) -> Result<&C, TecsError> {
+        self.try_entity(id)?
+            .get::<C>()
+            .ok_or_else(|| TecsError::missing_component::<C>(id))
+    }
+
+    /// Retrieve a mutable reference to a component from a given entity. Returns `None` if the
+    /// entity is despawned. The component is marked changed once it is dereferenced mutably, see
+    /// [`Mut`].
     ///
     /// # Note
     ///
     /// There is more optimal way to get components from an entity, see [`World::entity`].
-    pub fn get_mut<C: Component>(&mut self, id: EntityId) -> Option<&mut C> {
-        self.entity_mut(id).get::<C>()
+    pub fn get_mut<C: Component>(&mut self, id: EntityId) -> Option<Mut<'_, C>> {
+        self.try_get_mut::<C>(id).ok()
+    }
+
+    /// Fallible version of [`World::get_mut`] telling apart missing entities and missing
+    /// components.
+    pub fn try_get_mut<C: Component>(&mut self, id: EntityId) -> Result<Mut<'_, C>, TecsError> {
+        self.try_entity_mut(id)?
+            .into_mut::<C>()
+            .ok_or_else(|| TecsError::missing_component::<C>(id))
     }
 
     /// Convert lightweight entity id to a stronger handle. Can be used to retrieve components from
     /// an entity efficiently.
     ///
+    /// # Panics
+    ///
+    /// Panics if the entity is despawned.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -247,18 +1119,28 @@ impl World {
     /// assert_eq!(entity.get::<Rotation>(), None);
     /// ```
     pub fn entity(&self, id: EntityId) -> EntityHandle<'_> {
-        let location = self.locations[id as usize];
+        self.try_entity(id)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
 
-        EntityHandle {
+    /// Fallible version of [`World::entity`]. Fails if the entity is despawned.
+    pub fn try_entity(&self, id: EntityId) -> Result<EntityHandle<'_>, TecsError> {
+        let location = self.location(id).ok_or(TecsError::NoSuchEntity(id))?;
+
+        Ok(EntityHandle {
             id,
             entity_index: location.entity_index,
             archetype: &self.archetypes[location.archetype_index as usize],
-        }
+        })
     }
 
     /// Convert lightweight entity id to a stronger mutable handle. Can be used to retrieve and mutate
     /// components from an entity efficiently.
     ///
+    /// # Panics
+    ///
+    /// Panics if the entity is despawned.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -283,18 +1165,152 @@ impl World {
     ///
     /// *entity.get::<Position>().unwrap() = Position(0.0);
     ///
-    /// assert_eq!(entity.get::<Position>(), Some(&mut Position(0.0)));
-    /// assert_eq!(entity.get::<Velocity>(), Some(&mut Velocity(42.0)));
-    /// assert_eq!(entity.get::<Rotation>(), None);
+    /// assert_eq!(entity.get::<Position>().as_deref(), Some(&Position(0.0)));
+    /// assert_eq!(entity.get::<Velocity>().as_deref(), Some(&Velocity(42.0)));
+    /// assert!(entity.get::<Rotation>().is_none());
     /// ```
     pub fn entity_mut(&mut self, id: EntityId) -> EntityHandleMut<'_> {
-        let location = self.locations[id as usize];
+        self.try_entity_mut(id)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Fallible version of [`World::entity_mut`]. Fails if the entity is despawned.
+    pub fn try_entity_mut(&mut self, id: EntityId) -> Result<EntityHandleMut<'_>, TecsError> {
+        let location = self.location(id).ok_or(TecsError::NoSuchEntity(id))?;
+        let ticks = self.change_ticks();
 
-        EntityHandleMut {
+        Ok(EntityHandleMut {
             id,
             entity_index: location.entity_index,
             archetype: &mut self.archetypes[location.archetype_index as usize],
+            ticks,
+        })
+    }
+
+    /// Inserts a global resource into the world. Returns the previous value of the resource if
+    /// there was one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Resource};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct DeltaTime(f32);
+    /// impl Resource for DeltaTime {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// assert_eq!(world.insert_resource(DeltaTime(0.016)), None);
+    /// assert_eq!(world.insert_resource(DeltaTime(0.033)), Some(DeltaTime(0.016)));
+    ///
+    /// world.resource_mut::<DeltaTime>().0 *= 2.0;
+    ///
+    /// assert_eq!(world.resource::<DeltaTime>(), &DeltaTime(0.066));
+    /// ```
+    pub fn insert_resource<R: Resource>(&mut self, value: R) -> Option<R> {
+        self.resources.insert(value)
+    }
+
+    /// Removes a resource from the world returning its value.
+    pub fn remove_resource<R: Resource>(&mut self) -> Option<R> {
+        self.resources.remove::<R>()
+    }
+
+    /// Checks whether the world has a resource of type `R`.
+    pub fn contains_resource<R: Resource>(&self) -> bool {
+        self.resources.contains::<R>()
+    }
+
+    /// Retrieves a resource.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such resource in the world.
+    pub fn resource<R: Resource>(&self) -> &R {
+        self.try_resource::<R>()
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Fallible version of [`World::resource`].
+    pub fn try_resource<R: Resource>(&self) -> Result<&R, TecsError> {
+        self.resources
+            .get::<R>()
+            .ok_or_else(TecsError::missing_resource::<R>)
+    }
+
+    /// Retrieves a mutable reference to a resource.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such resource in the world.
+    pub fn resource_mut<R: Resource>(&mut self) -> &mut R {
+        self.try_resource_mut::<R>()
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Fallible version of [`World::resource_mut`].
+    pub fn try_resource_mut<R: Resource>(&mut self) -> Result<&mut R, TecsError> {
+        self.resources
+            .get_mut::<R>()
+            .ok_or_else(TecsError::missing_resource::<R>)
+    }
+
+    /// Adds the [`Events<E>`] resource updated by [`World::update_events`]. Does nothing if the
+    /// events are already added.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Event, EventCursor, Events};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Jump;
+    /// impl Event for Jump {}
+    ///
+    /// let mut world = World::new();
+    /// world.add_event::<Jump>();
+    ///
+    /// let mut cursor = EventCursor::new();
+    ///
+    /// world.send_event(Jump);
+    /// world.update_events();
+    /// assert_eq!(cursor.read(world.resource::<Events<Jump>>()).count(), 1);
+    ///
+    /// world.update_events();
+    /// assert!(world.resource::<Events<Jump>>().is_empty());
+    /// ```
+    pub fn add_event<E: Event>(&mut self) {
+        if !self.contains_resource::<Events<E>>() {
+            self.insert_resource(Events::<E>::new());
         }
+
+        self.event_updaters.insert(TypeId::of::<E>(), |world| {
+            if let Ok(events) = world.try_resource_mut::<Events<E>>() {
+                events.update();
+            }
+        });
+    }
+
+    /// Sends an event through the [`Events<E>`] resource.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the events are not added, see [`World::add_event`].
+    pub fn send_event<E: Event>(&mut self, event: E) {
+        self.resource_mut::<Events<E>>().send(event);
+    }
+
+    /// Updates every event queue added by [`World::add_event`], see [`Events::update`]. Should be
+    /// called once per frame. Queues removed from the resources are skipped.
+    pub fn update_events(&mut self) {
+        let updaters = std::mem::take(&mut self.event_updaters);
+
+        for update in updaters.values() {
+            update(self);
+        }
+
+        self.event_updaters = updaters;
     }
 }
 
@@ -336,10 +1352,34 @@ impl<'w> EntityHandle<'w> {
     /// assert_eq!(entity.get::<Rotation>(), None);
     /// ```
     pub fn get<C: Component>(&self) -> Option<&'w C> {
-        let &component_index = self.archetype.index.get(&TypeId::of::<C>())?;
-        let ptr = self.archetype.components[component_index];
+        let ptr = self.archetype.column_ptr::<C>()?;
+
+        Some(unsafe { &*ptr.add(self.entity_index as usize) })
+    }
 
-        Some(unsafe { ptr.cast::<C>().add(self.entity_index as usize).as_ref()? })
+    /// Checks if the entity has component `C`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// struct Frozen;
+    /// impl Component for Frozen {}
+    ///
+    /// struct Health(u32);
+    /// impl Component for Health {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let frozen = world.spawn((Frozen, Health(100)));
+    /// let not_frozen = world.spawn(Health(100));
+    ///
+    /// assert!(world.entity(frozen).has::<Frozen>());
+    /// assert!(!world.entity(not_frozen).has::<Frozen>());
+    /// ```
+    pub fn has<C: Component>(&self) -> bool {
+        self.archetype.contains::<C>()
     }
 
     /// Get the entity id.
@@ -353,6 +1393,7 @@ pub struct EntityHandleMut<'w> {
     pub(crate) id: EntityId,
     pub(crate) entity_index: u32,
     pub(crate) archetype: &'w mut Archetype,
+    pub(crate) ticks: ChangeTicks,
 }
 
 impl<'w> EntityHandleMut<'w> {
@@ -378,17 +1419,97 @@ impl<'w> EntityHandleMut<'w> {
     /// let mut world = World::new();
     ///
     /// let id = world.spawn((Position(-0.5), Velocity(42.0)));
-    /// let entity = world.entity(id);
+    /// let mut entity = world.entity_mut(id);
     ///
-    /// assert_eq!(entity.get::<Position>(), Some(&Position(-0.5)));
-    /// assert_eq!(entity.get::<Velocity>(), Some(&Velocity(42.0)));
-    /// assert_eq!(entity.get::<Rotation>(), None);
+    /// entity.get::<Position>().unwrap().0 = 0.0;
+    ///
+    /// assert_eq!(entity.get::<Position>().as_deref(), Some(&Position(0.0)));
+    /// assert_eq!(entity.get::<Velocity>().as_deref(), Some(&Velocity(42.0)));
+    /// assert!(entity.get::<Rotation>().is_none());
+    /// ```
+    ///
+    /// The returned reference borrows the handle, so the same component can not be borrowed
+    /// mutably twice.
+    ///
+    /// ```rust,compile_fail
+    /// use tecs::{World, Component};
+    ///
+    /// struct Position(f32);
+    /// impl Component for Position {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let id = world.spawn(Position(0.0));
+    /// let mut entity = world.entity_mut(id);
+    ///
+    /// let mut first = entity.get::<Position>().unwrap();
+    /// let second = entity.get::<Position>().unwrap();
+    ///
+    /// first.0 = second.0;
+    /// ```
+    pub fn get<C: Component>(&mut self) -> Option<Mut<'_, C>> {
+        self.get_many::<&mut C>()
+    }
+
+    /// Retrieve several components from an entity at once. Components are requested the same way
+    /// as in [`World::query_mut`]. Returns `None` if the entity lacks some of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some component is requested mutably more than once, e.g. `(&mut A, &A)`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tecs::{World, Component};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Position(f32);
+    /// impl Component for Position {}
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Velocity(f32);
+    /// impl Component for Velocity {}
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let id = world.spawn((Position(1.0), Velocity(2.0)));
+    /// let mut entity = world.entity_mut(id);
+    ///
+    /// let (mut position, velocity) = entity.get_many::<(&mut Position, &Velocity)>().unwrap();
+    /// position.0 += velocity.0;
+    ///
+    /// assert_eq!(entity.get::<Position>().as_deref(), Some(&Position(3.0)));
     /// ```
-    pub fn get<C: Component>(&mut self) -> Option<&'w mut C> {
-        let &component_index = self.archetype.index.get(&TypeId::of::<C>())?;
-        let ptr = self.archetype.components[component_index];
+    pub fn get_many<Q: Fetch + 'static>(&mut self) -> Option<Q::Item<'_>> {
+        query::assert_unique_access::<Q>();
+
+        // Safety: the handle is borrowed mutably and `Q` does not alias components
+        unsafe { self.fetch::<Q>() }
+    }
+
+    /// Checks if the entity has component `C`.
+    pub fn has<C: Component>(&self) -> bool {
+        self.archetype.contains::<C>()
+    }
+
+    /// Converts the handle into a mutable reference to a component of the entity.
+    pub fn into_mut<C: Component>(self) -> Option<Mut<'w, C>> {
+        // Safety: the handle is consumed
+        unsafe { self.fetch::<&mut C>() }
+    }
+
+    /// # Safety
+    ///
+    /// The returned items should not alias other borrows of the entity.
+    unsafe fn fetch<'h, Q: Fetch>(&self) -> Option<Q::Item<'h>> {
+        if !Q::matches(self.archetype) {
+            return None;
+        }
+
+        let state = Q::prepare(self.archetype, self.ticks);
 
-        Some(unsafe { ptr.cast::<C>().add(self.entity_index as usize).as_mut()? })
+        Some(unsafe { Q::fetch(state, self.entity_index as usize) })
     }
 
     /// Get the entity id.